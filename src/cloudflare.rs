@@ -0,0 +1,308 @@
+use parking_lot::Mutex;
+use reqwest::RequestBuilder;
+use serde::de::DeserializeOwned;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use crate::error::{ApiMessage, Error, NetworkKind};
+use crate::i18n;
+use crate::initialize::load_conf::{DnsRecord, RecordType};
+use crate::obj::CLIENT;
+
+pub const API_BASE: &str = "https://api.cloudflare.com/client/v4";
+
+#[derive(serde::Deserialize)]
+struct Envelope<T> {
+    #[serde(default)]
+    errors: Vec<ApiMessage>,
+    result: Option<T>,
+    result_info: Option<ResultInfo>,
+}
+
+#[derive(serde::Deserialize)]
+struct ResultInfo {
+    total_pages: u32,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct TokenStatus {
+    pub status: String,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct Zone {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct RecordInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: String,
+    pub content: String,
+    pub ttl: u32,
+    #[serde(default)]
+    pub proxied: bool,
+}
+
+fn network_error(error: reqwest::Error, path: &str) -> Error {
+    let kind = if error.is_timeout() {
+        NetworkKind::Timeout
+    } else if error.is_connect() {
+        NetworkKind::Connect
+    } else {
+        NetworkKind::Other
+    };
+    Error::Network {
+        kind,
+        message: i18n::api_network_error(path, error),
+    }
+}
+
+/// 请求体中的 DNS 记录，用于创建与更新
+#[derive(Debug, serde::Serialize)]
+pub struct RecordBody<'a> {
+    #[serde(rename = "type")]
+    pub record_type: &'static str,
+    pub name: &'a str,
+    pub content: String,
+    pub ttl: u32,
+    pub proxied: bool,
+}
+
+async fn send<T: DeserializeOwned>(
+    request: RequestBuilder,
+    path: &str,
+) -> Result<(T, Option<ResultInfo>), Error> {
+    let response = request.send().await.map_err(|e| network_error(e, path))?;
+
+    let status = response.status().as_u16();
+    let success = response.status().is_success();
+    let body = response
+        .json::<Envelope<T>>()
+        .await
+        .map_err(|e| network_error(e, path))?;
+
+    match body.result {
+        Some(result) if success => Ok((result, body.result_info)),
+        _ => Err(Error::Api {
+            status,
+            errors: body.errors,
+            message: i18n::api_request_failed(path, status),
+        }),
+    }
+}
+
+async fn get_page<T: DeserializeOwned>(
+    api_token: &str,
+    path: &str,
+) -> Result<(T, Option<ResultInfo>), Error> {
+    send(
+        CLIENT
+            .get(format!("{API_BASE}{path}"))
+            .bearer_auth(api_token),
+        path,
+    )
+    .await
+}
+
+pub async fn get<T: DeserializeOwned>(api_token: &str, path: &str) -> Result<T, Error> {
+    get_page(api_token, path).await.map(|(result, _)| result)
+}
+
+pub async fn post<T: DeserializeOwned>(
+    api_token: &str,
+    path: &str,
+    body: &impl serde::Serialize,
+) -> Result<T, Error> {
+    send(
+        CLIENT
+            .post(format!("{API_BASE}{path}"))
+            .bearer_auth(api_token)
+            .json(body),
+        path,
+    )
+    .await
+    .map(|(result, _)| result)
+}
+
+/// 按 result_info.total_pages 依次读取所有分页
+pub async fn get_all<T: DeserializeOwned>(api_token: &str, path: &str) -> Result<Vec<T>, Error> {
+    let separator = if path.contains('?') { '&' } else { '?' };
+    let mut items = Vec::new();
+    let mut page = 1;
+    loop {
+        let (result, info) = get_page::<Vec<T>>(
+            api_token,
+            &format!("{path}{separator}page={page}&per_page=50"),
+        )
+        .await?;
+        items.extend(result);
+        match info {
+            Some(info) if page < info.total_pages => page += 1,
+            _ => return Ok(items),
+        }
+    }
+}
+
+pub async fn verify_token(api_token: &str) -> Result<TokenStatus, Error> {
+    get(api_token, "/user/tokens/verify").await
+}
+
+pub async fn get_zone(api_token: &str, zone_id: &str) -> Result<Zone, Error> {
+    get(api_token, &format!("/zones/{zone_id}")).await
+}
+
+pub async fn get_record(api_token: &str, zone_id: &str, dns_id: &str) -> Result<RecordInfo, Error> {
+    get(api_token, &format!("/zones/{zone_id}/dns_records/{dns_id}")).await
+}
+
+pub async fn list_zones(api_token: &str) -> Result<Vec<Zone>, Error> {
+    get_all(api_token, "/zones").await
+}
+
+pub async fn list_records(api_token: &str, zone_id: &str) -> Result<Vec<RecordInfo>, Error> {
+    get_all(api_token, &format!("/zones/{zone_id}/dns_records")).await
+}
+
+pub async fn create_record(
+    api_token: &str,
+    zone_id: &str,
+    body: &RecordBody<'_>,
+) -> Result<RecordInfo, Error> {
+    post(api_token, &format!("/zones/{zone_id}/dns_records"), body).await
+}
+
+/// 按 (名称, 类型) 查找到的 (zone_id, dns_id)，进程内只查找一次
+type ResolvedIds = HashMap<(String, RecordType), (String, String)>;
+static RESOLVED: LazyLock<Mutex<ResolvedIds>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 重新加载配置后清除按名称查找的结果，使修改后的 zone_id 与 dns_id 生效
+pub fn clear_resolved() {
+    RESOLVED.lock().clear();
+}
+
+/// 返回 zone_id 与 dns_id 都已填写的记录，未填写的部分按 name 与类型查找
+pub async fn resolve(record: &DnsRecord) -> Result<DnsRecord, Error> {
+    if !record.zone_id.is_empty() && !record.dns_id.is_empty() {
+        return Ok(record.clone());
+    }
+    let name = record.name.trim_end_matches('.').to_ascii_lowercase();
+    let key = (name.clone(), record.record_type);
+    let cached = RESOLVED.lock().get(&key).cloned();
+    let (zone_id, dns_id) = match cached {
+        Some(ids) => ids,
+        None => {
+            let zone_id = if record.zone_id.is_empty() {
+                // 选择与名称匹配的最长区域
+                list_zones(record.api_token.expose())
+                    .await?
+                    .into_iter()
+                    .filter(|zone| {
+                        let zone_name = zone.name.to_ascii_lowercase();
+                        name == zone_name || name.ends_with(&format!(".{zone_name}"))
+                    })
+                    .max_by_key(|zone| zone.name.len())
+                    .map(|zone| zone.id)
+                    .ok_or_else(|| Error::Config(i18n::zone_not_found(&record.name)))?
+            } else {
+                record.zone_id.clone()
+            };
+            let dns_id = if record.dns_id.is_empty() {
+                get_all::<RecordInfo>(
+                    record.api_token.expose(),
+                    &format!(
+                        "/zones/{zone_id}/dns_records?type={}&name={name}",
+                        record.record_type.as_str()
+                    ),
+                )
+                .await?
+                .into_iter()
+                .next()
+                .map(|info| info.id)
+                .ok_or_else(|| {
+                    Error::Config(i18n::record_not_found(
+                        &record.name,
+                        record.record_type.as_str(),
+                    ))
+                })?
+            } else {
+                record.dns_id.clone()
+            };
+            RESOLVED
+                .lock()
+                .insert(key, (zone_id.clone(), dns_id.clone()));
+            (zone_id, dns_id)
+        }
+    };
+    Ok(DnsRecord {
+        zone_id,
+        dns_id,
+        ..record.clone()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const TOKEN: &str = "cf-test-token-0123456789abcdef";
+
+    /// 本地的 API 服务器：response 为 None 时读取请求后直接断开连接；返回收到的请求
+    async fn responder(
+        response: Option<&'static str>,
+    ) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let len = stream.read(&mut buf).await.unwrap();
+                request.extend(&buf[..len]);
+            }
+            if let Some(body) = response {
+                let reply = format!(
+                    "HTTP/1.1 401 Unauthorized\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                stream.write_all(reply.as_bytes()).await.unwrap();
+            }
+            String::from_utf8(request).unwrap()
+        });
+        (base, handle)
+    }
+
+    /// API 拒绝请求或连接中断时返回的错误都不能包含 token
+    #[tokio::test]
+    async fn api_errors_never_contain_token() {
+        let path = "/user/tokens/verify";
+        let client = reqwest::Client::new();
+        let body = r#"{"success":false,"errors":[{"code":1000,"message":"Invalid API Token"}],"result":null}"#;
+
+        let mut errors = Vec::new();
+        for response in [Some(body), None] {
+            let (base, handle) = responder(response).await;
+            let request = client.get(format!("{base}{path}")).bearer_auth(TOKEN);
+            let Err(error) = send::<TokenStatus>(request, path).await else {
+                panic!("the local API should reject the request");
+            };
+            // 确认 token 确实随请求发送
+            assert!(handle.await.unwrap().contains(TOKEN));
+            errors.push(error);
+        }
+        assert!(matches!(errors[0], Error::Api { status: 401, .. }));
+        assert!(matches!(errors[1], Error::Network { .. }));
+
+        for error in errors {
+            for output in [error.to_string(), format!("{error:?}")] {
+                assert!(!output.contains(TOKEN), "token leaked in {output}");
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use std::fmt::{self, Display};
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NetworkKind {
+    Timeout,
+    Connect,
+    Status(u16),
+    Other,
+}
+
+/// Cloudflare API 响应中 `errors` 数组的元素
+#[derive(Debug, serde::Deserialize, Clone)]
+pub struct ApiMessage {
+    pub code: u32,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// 配置文件缺失、格式错误或字段不合法
+    Config(String),
+    /// 获取到的响应中没有合法的 IP
+    Detection(String),
+    /// 超时、连接失败等网络层错误
+    Network { kind: NetworkKind, message: String },
+    /// Cloudflare API 返回了非 2xx 状态码
+    Api {
+        status: u16,
+        errors: Vec<ApiMessage>,
+        message: String,
+    },
+    /// 安装或卸载组件失败
+    Install(String),
+    /// 日志、tokio runtime 等运行环境错误
+    Runtime(String),
+}
+
+impl Error {
+    /// 超时、服务端 5xx 等稍后重试可能恢复的错误
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Error::Detection(_) => true,
+            Error::Network { kind, .. } => match kind {
+                NetworkKind::Status(status) => *status == 429 || *status >= 500,
+                NetworkKind::Timeout | NetworkKind::Connect | NetworkKind::Other => true,
+            },
+            Error::Api { status, .. } => *status == 429 || *status >= 500,
+            Error::Config(_) | Error::Install(_) | Error::Runtime(_) => false,
+        }
+    }
+
+    /// Cloudflare 鉴权失败（token 无效或权限不足）
+    pub fn is_auth(&self) -> bool {
+        match self {
+            Error::Api { status, errors, .. } => {
+                matches!(status, 401 | 403) || errors.iter().any(|e| matches!(e.code, 9109 | 10000))
+            }
+            _ => false,
+        }
+    }
+
+    /// 退出码参考 sysexits.h
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Error::Config(_) => 78,
+            Error::Detection(_) => 69,
+            Error::Network { .. } => 75,
+            Error::Api { .. } if self.is_auth() => 77,
+            Error::Api { .. } => 76,
+            Error::Install(_) => 73,
+            Error::Runtime(_) => 70,
+        })
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Config(message)
+            | Error::Detection(message)
+            | Error::Network { message, .. }
+            | Error::Install(message)
+            | Error::Runtime(message) => write!(f, "{message}"),
+            Error::Api {
+                errors, message, ..
+            } => {
+                write!(f, "{message}")?;
+                errors
+                    .iter()
+                    .try_for_each(|e| write!(f, " | [{}] {}", e.code, e.message))
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,915 @@
+use parking_lot::RwLock;
+use std::env;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+pub enum Lang {
+    #[serde(rename = "zh-CN", alias = "zh")]
+    ZhCn,
+    #[serde(rename = "en", alias = "en-US")]
+    En,
+}
+impl Lang {
+    /// 按 LC_ALL > LC_MESSAGES > LANG 的顺序读取系统语言，未设置时保持中文
+    fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|key| env::var(key).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::ZhCn, |value| {
+                if value.to_lowercase().starts_with("zh") {
+                    Lang::ZhCn
+                } else {
+                    Lang::En
+                }
+            })
+    }
+}
+
+static LANG: RwLock<Option<Lang>> = RwLock::new(None);
+
+pub fn lang() -> Lang {
+    if let Some(lang) = *LANG.read() {
+        return lang;
+    }
+    *LANG.write().get_or_insert_with(Lang::from_env)
+}
+
+pub fn set_lang(lang: Lang) {
+    *LANG.write() = Some(lang);
+}
+
+macro_rules! catalog {
+    ($($(#[$meta:meta])* $name:ident($($arg:ident: $ty:ty),*) { zh: $zh:literal, en: $en:literal $(,)? })*) => {
+        $(
+            $(#[$meta])*
+            pub fn $name($($arg: $ty),*) -> String {
+                match lang() {
+                    Lang::ZhCn => format!($zh),
+                    Lang::En => format!($en),
+                }
+            }
+        )*
+    };
+}
+
+// obj
+catalog! {
+    cannot_read_cwd() {
+        zh: "无法读取当前工作目录",
+        en: "Cannot read the current working directory",
+    }
+    cannot_read_exe_path() {
+        zh: "无法读取二进制文件路径",
+        en: "Cannot read the path of the executable",
+    }
+    cannot_read_exe_dir() {
+        zh: "无法读取二进制文件所在目录",
+        en: "Cannot read the directory of the executable",
+    }
+    log_level_invalid(e: impl Display) {
+        zh: "log 等级格式错误，参考 https://docs.rs/flexi_logger/latest/flexi_logger/struct.LogSpecification.html \n{e}",
+        en: "Invalid log level, see https://docs.rs/flexi_logger/latest/flexi_logger/struct.LogSpecification.html \n{e}",
+    }
+    logger_create_failed(e: impl Display) {
+        zh: "无法创建logger句柄,回溯错误:\n{e}",
+        en: "Cannot create the logger handle, caused by:\n{e}",
+    }
+    logger_initialized() {
+        zh: "日志初始化成功",
+        en: "Logger initialized",
+    }
+}
+
+// load_conf
+catalog! {
+    config_found(file: impl Display) {
+        zh: "找到 {file}",
+        en: "Found {file}",
+    }
+    config_not_found(searched: impl Display) {
+        zh: "找不到配置文件，已查找：{searched}",
+        en: "Cannot find a configuration file, searched: {searched}",
+    }
+    config_file_missing(file: impl Display) {
+        zh: "指定的配置文件 {file} 不存在",
+        en: "The specified configuration file {file} does not exist",
+    }
+    config_read_failed(file: impl Display, e: impl Display) {
+        zh: "无法读取 {file} | {e}",
+        en: "Cannot read {file} | {e}",
+    }
+    config_loaded(file: impl Display) {
+        zh: "使用配置文件 {file}",
+        en: "Using configuration file {file}",
+    }
+    config_from_env() {
+        zh: "使用环境变量中的配置",
+        en: "Using configuration from environment variables",
+    }
+    config_env() {
+        zh: "环境变量",
+        en: "environment variables",
+    }
+    config_not_table() {
+        zh: "配置文件的顶层与 dns_records 中的每一项都必须是表",
+        en: "The top level of the configuration and every dns_records entry must be a table",
+    }
+    env_invalid(name: &str, value: &str) {
+        zh: "环境变量 {name} 的值 \"{value}\" 不正确",
+        en: "Environment variable {name} has an invalid value \"{value}\"",
+    }
+    env_unknown(name: &str) {
+        zh: "无法识别环境变量 {name}，应为 DDNS_RECORDS_<下标>_<ACCOUNT|ZONE|API_TOKEN|API_TOKEN_FILE|API_TOKEN_ENV|ZONE_ID|DNS_ID|TYPE|NAME|TTL|PROXIED>",
+        en: "Unknown environment variable {name}, expected DDNS_RECORDS_<index>_<ACCOUNT|ZONE|API_TOKEN|API_TOKEN_FILE|API_TOKEN_ENV|ZONE_ID|DNS_ID|TYPE|NAME|TTL|PROXIED>",
+    }
+    env_index_gap(index: usize, len: usize) {
+        zh: "DDNS_RECORDS_{index}_* 的下标不连续，当前只有 {len} 条记录",
+        en: "DDNS_RECORDS_{index}_* skips an index, there are only {len} records so far",
+    }
+    interpolation_unclosed(text: &str) {
+        zh: "\"{text}\" 中的 ${{ 没有对应的 }}",
+        en: "Unclosed ${{ in \"{text}\"",
+    }
+    interpolation_missing(name: &str) {
+        zh: "配置引用的环境变量 {name} 未设置",
+        en: "Environment variable {name} referenced by the configuration is not set",
+    }
+    token_source_conflict(i: usize) {
+        zh: "第 {i} 条记录只能指定 api_token、api_token_file、api_token_env 中的一个",
+        en: "Record #{i} must set only one of api_token, api_token_file and api_token_env",
+    }
+    token_source_invalid(i: usize, field: &str) {
+        zh: "第 {i} 条记录的 {field} 必须是字符串",
+        en: "{field} of record #{i} must be a string",
+    }
+    token_file_read_failed(path: impl Display, e: impl Display) {
+        zh: "无法读取 token 文件 {path} | {e}",
+        en: "Cannot read token file {path} | {e}",
+    }
+    #[cfg(unix)]
+    config_world_readable(path: impl Display, mode: u32) {
+        zh: "{path} 中直接写有 API Token，但其他用户可以读取该文件（权限 {mode:o}），建议执行 chmod 600 或改用 api_token_file",
+        en: "{path} contains a literal API token but is readable by other users (mode {mode:o}), consider chmod 600 or api_token_file",
+    }
+    reference_unknown(i: usize, key: &str, name: impl Display) {
+        zh: "第 {i} 条记录引用的 {key} {name} 不存在",
+        en: "Record #{i} references an unknown {key} {name}",
+    }
+    config_invalid(file: impl Display, e: impl Display) {
+        zh: "{file} 格式不正确 | {e}",
+        en: "{file} is malformed | {e}",
+    }
+    config_parsed() {
+        zh: "成功解析配置文件",
+        en: "Configuration file parsed",
+    }
+    config_uninitialized() {
+        zh: "CONFIG 未初始化",
+        en: "CONFIG is not initialized",
+    }
+}
+
+// run
+catalog! {
+    exiting() {
+        zh: "退出中...",
+        en: "Exiting...",
+    }
+    #[cfg(unix)]
+    reload_requested() {
+        zh: "收到 SIGHUP，重新加载配置",
+        en: "Received SIGHUP, reloading configuration",
+    }
+    reload_done(source: impl Display) {
+        zh: "配置已重新加载，{source}",
+        en: "Configuration reloaded. {source}",
+    }
+    reload_failed(e: impl Display) {
+        zh: "重新加载配置失败，继续使用旧配置 | {e}",
+        en: "Failed to reload configuration, keeping the previous one | {e}",
+    }
+    reload_needs_restart(field: &str) {
+        zh: "{field} 的修改需要重启后才能生效，当前继续使用旧值",
+        en: "The change to {field} takes effect only after a restart, the previous value stays in use",
+    }
+    config_validation_failed(problems: impl Display) {
+        zh: "配置未通过检查：{problems}",
+        en: "The configuration failed validation: {problems}",
+    }
+    reload_invalid(problems: impl Display) {
+        zh: "新配置未通过检查：{problems}",
+        en: "The new configuration failed validation: {problems}",
+    }
+    force_refresh_round() {
+        zh: "已到达 force_refresh 间隔，本轮推送所有记录",
+        en: "force_refresh interval reached, pushing every record this round",
+    }
+    update_round_done() {
+        zh: "本次更新完成",
+        en: "Update round finished",
+    }
+    runtime_create_failed(e: impl Display) {
+        zh: "无法创建tokio runtime，回溯错误：{e}",
+        en: "Cannot create the tokio runtime, caused by: {e}",
+    }
+    signal_handler_failed(e: impl Display) {
+        zh: "无法创建系统信号处理器 | {e}",
+        en: "Cannot register the system signal handler | {e}",
+    }
+    #[cfg(windows)]
+    loop_signal_closed() {
+        zh: "LOOP_SIGNAL 已关闭",
+        en: "LOOP_SIGNAL is closed",
+    }
+    #[cfg(windows)]
+    service_force_exit() {
+        zh: "检测到服务环境，强制退出进程...",
+        en: "Running as a service, forcing the process to exit...",
+    }
+    #[cfg(windows)]
+    service_stopping() {
+        zh: "服务退出中...",
+        en: "Service stopping...",
+    }
+    #[cfg(windows)]
+    service_pause_received() {
+        zh: "收到暂停信号",
+        en: "Pause signal received",
+    }
+    #[cfg(windows)]
+    service_resuming() {
+        zh: "取消暂停，恢复运行...",
+        en: "Pause cancelled, resuming...",
+    }
+}
+
+// control
+catalog! {
+    #[cfg(unix)]
+    control_listen_failed(path: impl Display, e: impl Display) {
+        zh: "无法创建控制 socket {path}，ctl 命令不可用 | {e}",
+        en: "Cannot create the control socket {path}, ctl commands are unavailable | {e}",
+    }
+    #[cfg(unix)]
+    control_in_use(path: impl Display) {
+        zh: "控制 socket {path} 正被另一个实例使用，ctl 命令将发送给该实例",
+        en: "The control socket {path} is used by another instance, ctl commands will go to that instance",
+    }
+    #[cfg(unix)]
+    control_listening(path: impl Display) {
+        zh: "控制 socket 监听于 {path}",
+        en: "Control socket listening on {path}",
+    }
+    #[cfg(unix)]
+    control_accept_failed(e: impl Display) {
+        zh: "接受控制连接失败 | {e}",
+        en: "Failed to accept a control connection | {e}",
+    }
+    #[cfg(unix)]
+    control_paused() {
+        zh: "已暂停更新，使用 ddns_rust ctl resume 恢复",
+        en: "Updates paused, use ddns_rust ctl resume to continue",
+    }
+    #[cfg(unix)]
+    control_resumed() {
+        zh: "已恢复更新",
+        en: "Updates resumed",
+    }
+    #[cfg(unix)]
+    control_triggered() {
+        zh: "立即开始新一轮更新",
+        en: "Starting an update round now",
+    }
+    #[cfg(unix)]
+    control_stopping() {
+        zh: "程序正在退出",
+        en: "The program is shutting down",
+    }
+    control_trigger_paused() {
+        zh: "更新已暂停，请先使用 ddns_rust ctl resume 恢复",
+        en: "Updates are paused, use ddns_rust ctl resume first",
+    }
+    #[cfg(unix)]
+    control_state_running() {
+        zh: "运行中",
+        en: "running",
+    }
+    #[cfg(unix)]
+    control_state_paused() {
+        zh: "已暂停",
+        en: "paused",
+    }
+    #[cfg(unix)]
+    control_status(state: &str, pid: u32, config: &str, last_round: &str, next_round: &str) {
+        zh: "状态：{state}\n进程：{pid}\n配置：{config}\n上一轮：{last_round}\n下一轮：{next_round}",
+        en: "state: {state}\npid: {pid}\nconfig: {config}\nlast round: {last_round}\nnext round: {next_round}",
+    }
+    #[cfg(unix)]
+    control_unknown(command: &str) {
+        zh: "未知的控制命令：{command}",
+        en: "Unknown control command: {command}",
+    }
+    #[cfg(unix)]
+    ctl_connect_failed(path: impl Display, e: impl Display) {
+        zh: "无法连接 {path}，请确认 ddns_rust run --loops 正在运行且 --datadir 相同 | {e}",
+        en: "Cannot connect to {path}, make sure ddns_rust run --loops is running with the same --datadir | {e}",
+    }
+    #[cfg(unix)]
+    ctl_io_failed(e: impl Display) {
+        zh: "与运行中的实例通信失败 | {e}",
+        en: "Failed to communicate with the running instance | {e}",
+    }
+}
+
+// netwatch
+catalog! {
+    #[cfg(target_os = "linux")]
+    netwatch_failed(e: impl Display) {
+        zh: "无法监听网络变化，只按 delay 定期更新 | {e}",
+        en: "Cannot watch for network changes, updating every delay seconds only | {e}",
+    }
+    #[cfg(target_os = "linux")]
+    netwatch_listening() {
+        zh: "正在监听网络地址与路由变化",
+        en: "Watching for address and route changes",
+    }
+    #[cfg(target_os = "linux")]
+    network_changed() {
+        zh: "网络地址或默认路由已变化，立即开始新一轮更新",
+        en: "Network address or default route changed, starting an update round now",
+    }
+    #[cfg(target_os = "linux")]
+    network_changed_quiet() {
+        zh: "网络地址或默认路由已变化，当前处于静默时段，等到时段结束后再更新",
+        en: "Network address or default route changed, waiting for the quiet window to end",
+    }
+}
+
+// update_ip
+catalog! {
+    get_ip_timeout(v: u8) {
+        zh: "获取ipv{v}时链接超时",
+        en: "Timed out while fetching IPv{v}",
+    }
+    get_ip_connect_error(v: u8, e: impl Display) {
+        zh: "获取ipv{v}时链接错误{e}",
+        en: "Connection error while fetching IPv{v}: {e}",
+    }
+    get_ip_bad_url(v: u8, e: impl Display) {
+        zh: "获取ipv{v}的url不正确{e}",
+        en: "The URL used to fetch IPv{v} is invalid: {e}",
+    }
+    get_ip_unknown_error(v: u8, e: impl Display) {
+        zh: "获取ipv{v}时发生未定义错误{e}",
+        en: "Unexpected error while fetching IPv{v}: {e}",
+    }
+    get_ip_bad_status(v: u8, status: u16) {
+        zh: "获取ipv{v}时状态码不正确{status}",
+        en: "Unexpected status code while fetching IPv{v}: {status}",
+    }
+    get_ip_body_error(v: u8, e: impl Display) {
+        zh: "获取IPv{v}时响应正文时发生错误：{e}",
+        en: "Failed to read the response body while fetching IPv{v}: {e}",
+    }
+    get_ip_not_found(url: impl Display, v: u8) {
+        zh: "无法从{url}获取IPv{v}",
+        en: "No IPv{v} address found in the response of {url}",
+    }
+    get_ip_malformed(v: u8) {
+        zh: "获取到格式不正确的ipv{v}",
+        en: "Got a malformed IPv{v} address",
+    }
+    get_ip_json_invalid(url: impl Display, e: impl Display) {
+        zh: "{url} 返回的不是有效的 JSON | {e}",
+        en: "{url} did not return valid JSON | {e}",
+    }
+    server_lookup_failed(server: &str, e: impl Display) {
+        zh: "无法解析服务器 {server} 的地址 | {e}",
+        en: "Cannot resolve the address of server {server} | {e}",
+    }
+    server_family(server: &str, v: u8) {
+        zh: "服务器 {server} 没有 IPv{v} 地址，无法用于检测 IPv{v}",
+        en: "Server {server} has no IPv{v} address and cannot be used to detect IPv{v}",
+    }
+    dns_truncated(server: impl Display) {
+        zh: "{server} 的 UDP 响应被截断，改用 TCP",
+        en: "UDP response from {server} was truncated, retrying over TCP",
+    }
+    dns_udp_failed(server: impl Display, e: impl Display) {
+        zh: "通过 UDP 查询 {server} 失败，改用 TCP | {e}",
+        en: "UDP query to {server} failed, retrying over TCP | {e}",
+    }
+    dns_udp_timeout(server: impl Display) {
+        zh: "通过 UDP 查询 {server} 超时，改用 TCP",
+        en: "UDP query to {server} timed out, retrying over TCP",
+    }
+    dns_timeout(server: impl Display) {
+        zh: "查询 {server} 超时",
+        en: "Timed out while querying {server}",
+    }
+    dns_failed(server: impl Display, e: impl Display) {
+        zh: "查询 {server} 失败 | {e}",
+        en: "Failed to query {server} | {e}",
+    }
+    dns_malformed(server: impl Display) {
+        zh: "{server} 返回的 DNS 响应格式不正确",
+        en: "{server} returned a malformed DNS response",
+    }
+    dns_rcode(server: impl Display, rcode: u16, name: &str) {
+        zh: "{server} 返回错误码 {rcode} {name}",
+        en: "{server} answered with error code {rcode} {name}",
+    }
+    stun_failed(server: impl Display, e: impl Display) {
+        zh: "向 STUN 服务器 {server} 发送请求失败 | {e}",
+        en: "Failed to send a request to STUN server {server} | {e}",
+    }
+    stun_timeout(server: impl Display) {
+        zh: "STUN 服务器 {server} 没有响应",
+        en: "STUN server {server} did not respond",
+    }
+    stun_error(server: impl Display, e: impl Display) {
+        zh: "STUN 服务器 {server} 返回错误 | {e}",
+        en: "STUN server {server} returned an error | {e}",
+    }
+    stun_malformed() {
+        zh: "响应中没有映射地址",
+        en: "The response contains no mapped address",
+    }
+    get_ip_success(v: u8, ip: impl Display) {
+        zh: "获取成功，当前IPv{v}地址为：{ip}",
+        en: "Current IPv{v} address is {ip}",
+    }
+    source_backing_off(source: impl Display, time: impl Display) {
+        zh: "{source} 连续失败，{time} 前不再请求",
+        en: "{source} keeps failing, not requesting it before {time}",
+    }
+    retry_at(time: impl Display) {
+        zh: "将在 {time} 后重试",
+        en: "will retry after {time}",
+    }
+    record_backing_off(name: &str, record_type: &str, time: impl Display) {
+        zh: "{record_type} {name} 上次更新失败，{time} 后再重试",
+        en: "{record_type} {name} failed last time, retrying after {time}",
+    }
+    record_retry_scheduled(name: &str, record_type: &str, time: impl Display) {
+        zh: "{record_type} {name} 将在 {time} 后重试",
+        en: "{record_type} {name} will be retried after {time}",
+    }
+    address_denied(ip: IpAddr, net: impl Display) {
+        zh: "拒绝获取到的地址 {ip}：匹配拒绝规则 {net}",
+        en: "Rejected the detected address {ip}: it matches the deny rule {net}",
+    }
+    address_bogon(ip: IpAddr, net: impl Display) {
+        zh: "拒绝获取到的地址 {ip}：属于不可公网访问的保留范围 {net}，可能是强制门户或代理返回的，如确实需要请加入 allow 规则",
+        en: "Rejected the detected address {ip}: it is in the reserved range {net} that is not reachable from the internet, possibly returned by a captive portal or proxy; add an allow rule if this is intended",
+    }
+    address_not_global(ip: IpAddr) {
+        zh: "拒绝获取到的地址 {ip}：不在全局单播范围 2000::/3 内，可能是本地或映射地址，如确实需要请加入 ipv6_allow 规则",
+        en: "Rejected the detected address {ip}: it is outside the global unicast range 2000::/3, possibly a local or mapped address; add an ipv6_allow rule if this is intended",
+    }
+    address_cgnat_rejected(ip: IpAddr) {
+        zh: "拒绝获取到的地址 {ip}：属于运营商级 NAT 共享地址 100.64.0.0/10，外部无法访问，如确实需要请在 ipv4_allow 中加入该范围",
+        en: "Rejected the detected address {ip}: it is a carrier-grade NAT address in 100.64.0.0/10 and not reachable from outside; add the range to ipv4_allow if this is intended",
+    }
+    address_cgnat(ip: IpAddr) {
+        zh: "获取到的地址 {ip} 属于运营商级 NAT 共享地址，外部可能无法通过该地址访问",
+        en: "The detected address {ip} is a carrier-grade NAT address and may not be reachable from outside",
+    }
+    ip_pending(v: u8, ip: impl Display, checks: u32, seconds: i64) {
+        zh: "检测到新的IPv{v}地址 {ip}，等待其稳定后再推送（已连续检测到 {checks} 次，持续 {seconds} 秒）",
+        en: "New IPv{v} address {ip} is pending until it is stable (seen {checks} times in a row over {seconds}s)",
+    }
+    ip_unchanged(v: u8) {
+        zh: "IPv{v}地址未改变，只重试上次失败的记录",
+        en: "IPv{v} address unchanged, only retrying records that failed",
+    }
+    no_records(record_type: &str) {
+        zh: "没有需要更新的{record_type}记录",
+        en: "No {record_type} records to update",
+    }
+    update_success(body: &str) {
+        zh: " 成功: {body}",
+        en: " Success: {body}",
+    }
+    update_bad_status(name: &str, record_type: &str, status: u16) {
+        zh: "更新:{name},类型:{record_type}时服务器返回码:{status}",
+        en: "Server returned {status} while updating {name} ({record_type})",
+    }
+    update_timeout(name: &str, record_type: &str) {
+        zh: "更新:{name},类型:{record_type}时链接超时",
+        en: "Timed out while updating {name} ({record_type})",
+    }
+    update_connect_error(name: &str, record_type: &str) {
+        zh: "更新:{name},类型:{record_type}时链接错误",
+        en: "Connection error while updating {name} ({record_type})",
+    }
+    update_unknown_error(name: &str, record_type: &str, e: impl Display) {
+        zh: "更新{name}类型{record_type}时发生未知错误:{e}",
+        en: "Unexpected error while updating {name} ({record_type}): {e}",
+    }
+}
+
+// setup
+catalog! {
+    service_create_failed(e: impl Display) {
+        zh: "创建服务失败，回溯错误：{e}",
+        en: "Failed to create the service, caused by: {e}",
+    }
+    service_create_need_admin() {
+        zh: "创建服务失败，请检查是否有管理员权限",
+        en: "Failed to create the service, please check for administrator privileges",
+    }
+    systemd_service_write_failed(e: impl Display) {
+        zh: "创建服务失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to create the service, please check for administrator privileges, caused by: {e}",
+    }
+    schedule_create_failed(e: impl Display) {
+        zh: "创建计划任务失败，回溯错误：{e}",
+        en: "Failed to create the scheduled task, caused by: {e}",
+    }
+    schedule_create_need_admin() {
+        zh: "创建计划任务失败，请检查是否有管理员权限",
+        en: "Failed to create the scheduled task, please check for administrator privileges",
+    }
+    systemd_timer_write_failed(e: impl Display) {
+        zh: "创建systemd timer失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to create the systemd timer, please check for administrator privileges, caused by: {e}",
+    }
+    service_delete_failed(e: impl Display) {
+        zh: "删除服务失败，回溯错误：{e}",
+        en: "Failed to delete the service, caused by: {e}",
+    }
+    service_delete_need_admin() {
+        zh: "删除服务失败，请检查是否有管理员权限",
+        en: "Failed to delete the service, please check for administrator privileges",
+    }
+    systemd_service_delete_failed(e: impl Display) {
+        zh: "删除服务文件失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to delete the service file, please check for administrator privileges, caused by: {e}",
+    }
+    schedule_delete_failed(e: impl Display) {
+        zh: "删除计划任务失败，回溯错误：{e}",
+        en: "Failed to delete the scheduled task, caused by: {e}",
+    }
+    schedule_delete_need_admin() {
+        zh: "删除计划任务失败，请检查是否有管理员权限",
+        en: "Failed to delete the scheduled task, please check for administrator privileges",
+    }
+    systemd_timer_delete_failed(e: impl Display) {
+        zh: "删除systemd timer失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to delete the systemd timer, please check for administrator privileges, caused by: {e}",
+    }
+}
+
+// cloudflare
+catalog! {
+    zone_not_found(name: &str) {
+        zh: "找不到包含 {name} 的区域，请检查 API Token 的权限",
+        en: "No zone contains {name}, please check the permissions of the API token",
+    }
+    record_not_found(name: &str, record_type: &str) {
+        zh: "区域中没有 {name} 的 {record_type} 记录，请先在 Cloudflare 上创建",
+        en: "No {record_type} record named {name} in the zone, please create it on Cloudflare first",
+    }
+    api_network_error(path: &str, e: impl Display) {
+        zh: "请求 Cloudflare API {path} 时网络错误：{e}",
+        en: "Network error while requesting Cloudflare API {path}: {e}",
+    }
+    api_request_failed(path: &str, status: u16) {
+        zh: "请求 Cloudflare API {path} 失败，服务器返回码:{status}",
+        en: "Cloudflare API {path} failed with status {status}",
+    }
+}
+
+// config check
+catalog! {
+    delay_zero() {
+        zh: "delay 必须大于 0",
+        en: "delay must be greater than 0",
+    }
+    quiet_window_invalid(value: &str) {
+        zh: "静默时段 \"{value}\" 格式不正确，应为 \"HH:MM-HH:MM\"",
+        en: "Quiet window \"{value}\" is invalid, expected \"HH:MM-HH:MM\"",
+    }
+    stable_for_invalid(value: &str) {
+        zh: "stable_for \"{value}\" 格式不正确，应为大于 0 的次数或带 s、m、h 后缀的时长",
+        en: "stable_for \"{value}\" is invalid, expected a number of checks greater than 0 or a duration with an s, m or h suffix",
+    }
+    source_invalid(value: &str, presets: impl Display) {
+        zh: "IP 来源 \"{value}\" 既不是预设名称也不是 URL，可用的预设：{presets}",
+        en: "IP source \"{value}\" is neither a preset nor a URL, available presets: {presets}",
+    }
+    source_parser_unknown(parser: &str) {
+        zh: "无法识别解析方式 \"{parser}\"，应为 plain、regex、json 或 kv",
+        en: "Unknown parser \"{parser}\", expected plain, regex, json or kv",
+    }
+    source_field_missing(parser: &str, field: &str) {
+        zh: "解析方式 {parser} 需要指定 {field}",
+        en: "Parser {parser} requires {field}",
+    }
+    source_pattern_invalid(pattern: &str, e: impl Display) {
+        zh: "正则表达式 \"{pattern}\" 不正确 | {e}",
+        en: "Regular expression \"{pattern}\" is invalid | {e}",
+    }
+    source_pointer_invalid(pointer: &str) {
+        zh: "JSON Pointer \"{pointer}\" 必须为空或以 / 开头，例如 \"/{pointer}\"",
+        en: "JSON Pointer \"{pointer}\" must be empty or start with /, e.g. \"/{pointer}\"",
+    }
+    source_pattern_no_group(pattern: &str) {
+        zh: "正则表达式 \"{pattern}\" 中没有名为 ip 的捕获组",
+        en: "Regular expression \"{pattern}\" has no capture group named ip",
+    }
+    source_dns_type_unknown(value: &str) {
+        zh: "无法识别 DNS 记录类型 \"{value}\"，应为 A、AAAA 或 TXT",
+        en: "Unknown DNS record type \"{value}\", expected A, AAAA or TXT",
+    }
+    source_stun_empty() {
+        zh: "stun: 之后需要指定服务器",
+        en: "A server is required after stun:",
+    }
+    source_dns_name_invalid(value: &str) {
+        zh: "DNS 查询名称 \"{value}\" 不正确",
+        en: "DNS query name \"{value}\" is invalid",
+    }
+    source_dns_class_unknown(value: &str) {
+        zh: "无法识别 DNS 类别 \"{value}\"，应为 IN 或 CH",
+        en: "Unknown DNS class \"{value}\", expected IN or CH",
+    }
+    retry_delay_zero() {
+        zh: "retry_delay 必须大于 0",
+        en: "retry_delay must be greater than 0",
+    }
+    max_retry_delay_too_small() {
+        zh: "max_retry_delay 不能小于 retry_delay",
+        en: "max_retry_delay must not be less than retry_delay",
+    }
+    interval_too_large(field: &str, seconds: u64, max: u64) {
+        zh: "{field} 不能超过 {max} 秒，当前为 {seconds}",
+        en: "{field} must not exceed {max} seconds, got {seconds}",
+    }
+    jitter_invalid(jitter: u8) {
+        zh: "jitter 为百分比，必须在 0 到 100 之间，当前为 {jitter}",
+        en: "jitter is a percentage and must be between 0 and 100, got {jitter}",
+    }
+    no_records_configured() {
+        zh: "dns_records 为空",
+        en: "dns_records is empty",
+    }
+    token_empty() {
+        zh: "api_token 为空",
+        en: "api_token is empty",
+    }
+    id_invalid(field: &str, value: &str) {
+        zh: "{field} \"{value}\" 不是 32 位十六进制 ID",
+        en: "{field} \"{value}\" is not a 32-character hexadecimal ID",
+    }
+    ttl_invalid(ttl: u32) {
+        zh: "ttl {ttl} 不合法，只能为 1（自动）或 60-86400",
+        en: "ttl {ttl} is invalid, must be 1 (automatic) or 60-86400",
+    }
+    name_invalid(name: &str) {
+        zh: "name \"{name}\" 不是合法的完整域名",
+        en: "name \"{name}\" is not a valid fully qualified domain name",
+    }
+    duplicate_record(index: usize) {
+        zh: "与 #{index} 的名称和类型重复",
+        en: "duplicates the name and type of #{index}",
+    }
+    duplicate_dns_id(index: usize) {
+        zh: "与 #{index} 的 dns_id 重复",
+        en: "duplicates the dns_id of #{index}",
+    }
+    token_inactive(status: &str) {
+        zh: "api_token 状态为 {status}，不可用",
+        en: "api_token is {status}, not active",
+    }
+    token_verify_failed(e: impl Display) {
+        zh: "api_token 校验失败：{e}",
+        en: "api_token verification failed: {e}",
+    }
+    zone_lookup_failed(e: impl Display) {
+        zh: "无法读取 zone_id 对应的区域：{e}",
+        en: "Cannot read the zone of zone_id: {e}",
+    }
+    name_outside_zone(name: &str, zone: &str) {
+        zh: "{name} 不属于区域 {zone}",
+        en: "{name} does not belong to zone {zone}",
+    }
+    record_lookup_failed(e: impl Display) {
+        zh: "无法读取 dns_id 对应的记录：{e}",
+        en: "Cannot read the record of dns_id: {e}",
+    }
+    record_name_mismatch(expected: &str, actual: &str) {
+        zh: "dns_id 对应的记录名称为 {actual}，与配置的 {expected} 不一致",
+        en: "dns_id points to {actual}, not the configured {expected}",
+    }
+    record_type_mismatch(expected: &str, actual: &str) {
+        zh: "dns_id 对应的记录类型为 {actual}，与配置的 {expected} 不一致",
+        en: "dns_id points to a {actual} record, not the configured {expected}",
+    }
+    check_global() {
+        zh: "全局配置",
+        en: "global",
+    }
+    check_passed(count: usize) {
+        zh: "配置检查通过，共 {count} 条记录",
+        en: "Configuration OK, {count} record(s)",
+    }
+    check_failed(count: usize) {
+        zh: "配置检查发现 {count} 个问题",
+        en: "Configuration check found {count} problem(s)",
+    }
+}
+
+// records
+catalog! {
+    records_no_token() {
+        zh: "没有可用的 API Token，请使用 --token 指定或在配置文件中配置",
+        en: "No API token available, pass --token or configure one in the configuration file",
+    }
+    records_no_zone() {
+        zh: "Token 无权访问任何区域",
+        en: "The token cannot access any zone",
+    }
+    records_zone_empty() {
+        zh: "（没有 A/AAAA 记录）",
+        en: "(no A/AAAA records)",
+    }
+    records_serialize_failed(e: impl Display) {
+        zh: "无法生成配置：{e}",
+        en: "Cannot generate the configuration: {e}",
+    }
+}
+
+// init
+catalog! {
+    wizard_io_failed(e: impl Display) {
+        zh: "读写失败：{e}",
+        en: "I/O error: {e}",
+    }
+    wizard_input_closed() {
+        zh: "输入已结束，配置未保存",
+        en: "Input closed, the configuration was not saved",
+    }
+    wizard_invalid_input() {
+        zh: "输入不正确，请重新输入",
+        en: "Invalid input, please try again",
+    }
+    wizard_token_prompt() {
+        zh: "请输入 API Token（输入内容不会显示）",
+        en: "API token (input is hidden)",
+    }
+    wizard_token_ok() {
+        zh: "Token 校验通过",
+        en: "Token verified",
+    }
+    wizard_select_zones() {
+        zh: "选择要使用的区域，多个序号用逗号分隔，all 表示全部",
+        en: "Zones to use, comma separated numbers or all",
+    }
+    wizard_select_records() {
+        zh: "选择要更新的记录，多个序号用逗号分隔，all 表示全部，留空跳过",
+        en: "Records to update, comma separated numbers or all, empty to skip",
+    }
+    wizard_ttl() {
+        zh: "ttl（1 表示自动，或 60-86400）",
+        en: "ttl (1 for automatic, or 60-86400)",
+    }
+    wizard_proxied() {
+        zh: "是否使用 CDN 代理",
+        en: "Proxy through Cloudflare",
+    }
+    wizard_new_record(zone: &str) {
+        zh: "在 {zone} 中新建记录的名称，留空结束",
+        en: "Name of a new record in {zone}, empty to finish",
+    }
+    wizard_record_type() {
+        zh: "记录类型（A 或 AAAA）",
+        en: "Record type (A or AAAA)",
+    }
+    wizard_record_created(name: &str, id: &str) {
+        zh: "已创建 {name}，dns_id: {id}",
+        en: "Created {name}, dns_id: {id}",
+    }
+    wizard_delay() {
+        zh: "循环周期（秒）",
+        en: "Update interval in seconds",
+    }
+    wizard_ip_url(v: u8) {
+        zh: "获取 IPv{v} 的地址",
+        en: "URL used to detect IPv{v}",
+    }
+    wizard_confirm(path: impl Display, records: usize, new_records: usize) {
+        zh: "将 {records} 条记录写入 {path}，并用当前 IP 在 Cloudflare 上新建其中 {new_records} 条，是否继续",
+        en: "Write {records} records to {path} and create {new_records} of them on Cloudflare with the current IP",
+    }
+    wizard_create_failed() {
+        zh: "配置已保存，但新记录没有全部创建成功，请在 Cloudflare 面板上创建剩余的记录，或重新运行 ddns_rust init",
+        en: "The configuration was saved but not all new records were created, create the rest in the Cloudflare dashboard or run ddns_rust init again",
+    }
+    wizard_overwrite(path: impl Display) {
+        zh: "{path} 已存在，是否覆盖",
+        en: "{path} already exists, overwrite it",
+    }
+    wizard_written(path: impl Display) {
+        zh: "配置已写入 {path}",
+        en: "Configuration written to {path}",
+    }
+}
+
+// dry run
+catalog! {
+    plan_changed(record_type: &str, name: &str, changes: &str) {
+        zh: "{record_type} {name} {changes}",
+        en: "{record_type} {name} {changes}",
+    }
+    plan_content(from: &str, to: &str) {
+        zh: "{from} -> {to}",
+        en: "{from} -> {to}",
+    }
+    plan_name(from: &str, to: &str) {
+        zh: "名称 {from} -> {to}",
+        en: "name {from} -> {to}",
+    }
+    plan_ttl(from: u32, to: u32) {
+        zh: "ttl {from} -> {to}",
+        en: "ttl {from} -> {to}",
+    }
+    plan_proxied(from: bool, to: bool) {
+        zh: "代理 {from} -> {to}",
+        en: "proxied {from} -> {to}",
+    }
+    plan_unchanged(record_type: &str, name: &str, content: &str) {
+        zh: "{record_type} {name} {content}（无变化）",
+        en: "{record_type} {name} {content} (no change)",
+    }
+}
+
+// state
+catalog! {
+    state_invalid(path: impl Display, e: impl Display) {
+        zh: "状态文件 {path} 格式不正确 | {e}",
+        en: "State file {path} is malformed | {e}",
+    }
+    state_read_failed(path: impl Display, e: impl Display) {
+        zh: "无法读取状态文件 {path} | {e}",
+        en: "Cannot read state file {path} | {e}",
+    }
+    state_write_failed(path: impl Display, e: impl Display) {
+        zh: "无法写入状态文件 {path} | {e}",
+        en: "Cannot write state file {path} | {e}",
+    }
+    state_saved() {
+        zh: "状态已保存",
+        en: "State saved",
+    }
+}
+
+// status
+catalog! {
+    status_no_records(path: impl Display) {
+        zh: "{path} 中还没有记录的更新状态，请先运行 ddns_rust run",
+        en: "No record updates in {path} yet, run ddns_rust run first",
+    }
+    status_pending(ip: impl Display, since: impl Display, checks: u32, seconds: i64) {
+        zh: "等待稳定：{ip}，自 {since} 起连续检测到 {checks} 次（{seconds} 秒）",
+        en: "pending: {ip}, seen {checks} times in a row since {since} ({seconds}s)",
+    }
+}
+
+// update
+catalog! {
+    no_record_selected() {
+        zh: "没有符合条件的记录",
+        en: "No record matches the selection",
+    }
+}
+
+// generate
+catalog! {
+    manpage_render_failed(e: impl Display) {
+        zh: "生成 man 手册失败：{e}",
+        en: "Failed to render the man page: {e}",
+    }
+    output_write_failed(e: impl Display) {
+        zh: "写入标准输出失败：{e}",
+        en: "Failed to write to stdout: {e}",
+    }
+    #[cfg(unix)]
+    generated_write_failed(path: &str, e: impl Display) {
+        zh: "写入 {path} 失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to write {path}, please check for administrator privileges, caused by: {e}",
+    }
+    #[cfg(unix)]
+    generated_delete_failed(path: &str, e: impl Display) {
+        zh: "删除 {path} 失败，请检查是否有管理员权限，回溯错误：{e}",
+        en: "Failed to delete {path}, please check for administrator privileges, caused by: {e}",
+    }
+    #[cfg(unix)]
+    generated_installed(path: &str) {
+        zh: "已写入 {path}",
+        en: "Written to {path}",
+    }
+}
@@ -1,2 +1,6 @@
+pub mod accounts;
+pub mod env_conf;
 pub mod load_conf;
-pub mod parse_args;
\ No newline at end of file
+pub mod parse_args;
+pub mod secrets;
+pub mod sources;
@@ -0,0 +1,104 @@
+use serde_json::{Map, Value};
+
+use crate::error::Error;
+use crate::i18n;
+
+/// 可以写在 account 中、由记录继承的 token 字段
+const TOKEN_FIELDS: [&str; 3] = ["api_token", "api_token_file", "api_token_env"];
+
+/// 从配置中取出 accounts / zones 表，展开后的配置中不再保留
+fn take_table(config: &mut Map<String, Value>, key: &str) -> Result<Map<String, Value>, Error> {
+    match config.remove(key) {
+        None => Ok(Map::new()),
+        Some(Value::Object(map)) => Ok(map),
+        Some(_) => Err(Error::Config(i18n::config_not_table())),
+    }
+}
+
+/// 引用的 account / zone 名称
+fn reference<'a>(
+    record: &Map<String, Value>,
+    key: &str,
+    table: &'a Map<String, Value>,
+    i: usize,
+) -> Result<Option<&'a Map<String, Value>>, Error> {
+    let Some(name) = record.get(key) else {
+        return Ok(None);
+    };
+    name.as_str()
+        .and_then(|name| table.get(name))
+        .and_then(Value::as_object)
+        .map(Some)
+        .ok_or_else(|| Error::Config(i18n::reference_unknown(i, key, name)))
+}
+
+/// 相对名称加上区域名，"@" 表示区域本身，以 "." 结尾或已包含区域名的视为完整域名
+fn expand_name(name: &str, zone_name: &str) -> String {
+    let lower = name.to_ascii_lowercase();
+    let zone_lower = zone_name.trim_end_matches('.').to_ascii_lowercase();
+    if name == "@" {
+        zone_name.trim_end_matches('.').to_string()
+    } else if name.ends_with('.') || lower == zone_lower || lower.ends_with(&format!(".{zone_lower}"))
+    {
+        name.to_string()
+    } else {
+        format!("{name}.{}", zone_name.trim_end_matches('.'))
+    }
+}
+
+/// 把 [accounts.x] 与 [zones.y] 展开到引用它们的记录中，记录中已有的字段优先
+pub fn expand(value: &mut Value) -> Result<(), Error> {
+    let Value::Object(config) = value else {
+        return Err(Error::Config(i18n::config_not_table()));
+    };
+    let accounts = take_table(config, "accounts")?;
+    let zones = take_table(config, "zones")?;
+    let Some(Value::Array(records)) = config.get_mut("dns_records") else {
+        return Ok(());
+    };
+
+    for (i, record) in records.iter_mut().enumerate() {
+        let Value::Object(record) = record else {
+            return Err(Error::Config(i18n::config_not_table()));
+        };
+        let zone = reference(record, "zone", &zones, i)?;
+
+        // 记录自己的 account 优先于区域的 account
+        let account = match reference(record, "account", &accounts, i)? {
+            Some(account) => Some(account),
+            None => match zone {
+                Some(zone) => reference(zone, "account", &accounts, i)?,
+                None => None,
+            },
+        };
+        if let Some(account) = account
+            && TOKEN_FIELDS.iter().all(|field| !record.contains_key(*field))
+        {
+            TOKEN_FIELDS
+                .iter()
+                .filter_map(|field| account.get(*field).map(|token| (field, token)))
+                .for_each(|(field, token)| {
+                    record.insert(field.to_string(), token.clone());
+                });
+        }
+
+        if let Some(zone) = zone {
+            for field in ["zone_id", "ttl", "proxied"] {
+                if let Some(default) = zone.get(field) {
+                    record
+                        .entry(field)
+                        .or_insert_with(|| default.clone());
+                }
+            }
+            if let (Some(Value::String(name)), Some(zone_name)) =
+                (record.get("name"), zone.get("name").and_then(Value::as_str))
+            {
+                let name = expand_name(name, zone_name);
+                record.insert("name".to_string(), Value::from(name));
+            }
+        }
+        record.remove("zone");
+        record.remove("account");
+    }
+    Ok(())
+}
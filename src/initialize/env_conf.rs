@@ -0,0 +1,216 @@
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::error::Error;
+use crate::i18n;
+
+#[derive(Clone, Copy)]
+enum Kind {
+    String,
+    Integer,
+    Bool,
+    RecordType,
+    /// 逗号分隔的列表
+    List,
+}
+
+/// 全局字段对应的环境变量
+const GLOBAL_VARS: [(&str, &str, Kind); 21] = [
+    ("DDNS_DELAY", "delay", Kind::Integer),
+    ("DDNS_RETRY_DELAY", "retry_delay", Kind::Integer),
+    ("DDNS_MAX_RETRY_DELAY", "max_retry_delay", Kind::Integer),
+    ("DDNS_JITTER", "jitter", Kind::Integer),
+    ("DDNS_SCHEDULE", "schedule", Kind::String),
+    ("DDNS_QUIET_HOURS", "quiet_hours", Kind::List),
+    ("DDNS_FORCE_REFRESH", "force_refresh", Kind::Integer),
+    ("DDNS_STABLE_FOR", "stable_for", Kind::String),
+    ("DDNS_MULTI_THREAD", "mutli_thread", Kind::Bool),
+    ("DDNS_WATCH_NETWORK", "watch_network", Kind::Bool),
+    ("DDNS_NETWORK_DEBOUNCE", "network_debounce", Kind::Integer),
+    ("DDNS_LOG_LEVEL", "log_level", Kind::String),
+    ("DDNS_IPV4_URL", "ipv4_url", Kind::String),
+    ("DDNS_IPV6_URL", "ipv6_url", Kind::String),
+    ("DDNS_IPV4_SOURCES", "ipv4_sources", Kind::List),
+    ("DDNS_IPV6_SOURCES", "ipv6_sources", Kind::List),
+    ("DDNS_IPV4_ALLOW", "ipv4_allow", Kind::List),
+    ("DDNS_IPV4_DENY", "ipv4_deny", Kind::List),
+    ("DDNS_IPV6_ALLOW", "ipv6_allow", Kind::List),
+    ("DDNS_IPV6_DENY", "ipv6_deny", Kind::List),
+    ("DDNS_LANG", "lang", Kind::String),
+];
+
+/// DDNS_RECORDS_<下标>_<后缀> 中的后缀与记录字段
+const RECORD_VARS: [(&str, &str, Kind); 11] = [
+    ("ACCOUNT", "account", Kind::String),
+    ("ZONE", "zone", Kind::String),
+    ("API_TOKEN", "api_token", Kind::String),
+    ("API_TOKEN_FILE", "api_token_file", Kind::String),
+    ("API_TOKEN_ENV", "api_token_env", Kind::String),
+    ("ZONE_ID", "zone_id", Kind::String),
+    ("DNS_ID", "dns_id", Kind::String),
+    ("TYPE", "type", Kind::RecordType),
+    ("NAME", "name", Kind::String),
+    ("TTL", "ttl", Kind::Integer),
+    ("PROXIED", "proxied", Kind::Bool),
+];
+
+const RECORD_PREFIX: &str = "DDNS_RECORDS_";
+
+fn var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn parse(name: &str, raw: &str, kind: Kind) -> Result<Value, Error> {
+    let invalid = || Error::Config(i18n::env_invalid(name, raw));
+    Ok(match kind {
+        Kind::String => Value::from(raw),
+        Kind::Integer => Value::from(raw.trim().parse::<u64>().map_err(|_| invalid())?),
+        Kind::Bool => Value::from(match raw.trim().to_ascii_lowercase().as_str() {
+            "1" | "true" | "yes" | "on" => true,
+            "0" | "false" | "no" | "off" => false,
+            _ => return Err(invalid()),
+        }),
+        Kind::RecordType => match raw.trim().to_ascii_uppercase().as_str() {
+            record_type @ ("A" | "AAAA") => Value::from(record_type),
+            _ => return Err(invalid()),
+        },
+        Kind::List => Value::from(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .collect::<Vec<_>>(),
+        ),
+    })
+}
+
+/// 是否通过 DDNS_RECORDS 或 DDNS_RECORDS_<下标>_* 定义了记录，此时可以没有配置文件
+pub fn has_records() -> bool {
+    var("DDNS_RECORDS").is_some()
+        || env::vars_os().any(|(name, _)| {
+            name.to_str()
+                .is_some_and(|name| name.starts_with(RECORD_PREFIX))
+        })
+}
+
+/// 用环境变量覆盖配置文件中的值，config 为空对象时即完全由环境变量组成
+///
+/// 返回第一条由环境变量新增的记录的下标，展开 accounts / zones 后交给 [`fill`]
+pub fn apply(config: &mut Value) -> Result<usize, Error> {
+    let Value::Object(config) = config else {
+        return Err(Error::Config(i18n::config_not_table()));
+    };
+
+    for (name, field, kind) in GLOBAL_VARS {
+        if let Some(raw) = var(name) {
+            config.insert(field.to_string(), parse(name, &raw, kind)?);
+        }
+    }
+
+    let Value::Array(records) = config
+        .entry("dns_records")
+        .or_insert_with(|| Value::Array(Vec::new()))
+    else {
+        return Err(Error::Config(i18n::config_not_table()));
+    };
+    let first_new = records.len();
+
+    // DDNS_RECORDS=A:example.com,AAAA:www.example.com
+    if let Some(compact) = var("DDNS_RECORDS") {
+        for item in compact.split(',').map(str::trim).filter(|item| !item.is_empty()) {
+            let (record_type, name) = item
+                .split_once(':')
+                .ok_or_else(|| Error::Config(i18n::env_invalid("DDNS_RECORDS", item)))?;
+            let mut record = Map::new();
+            record.insert(
+                "type".to_string(),
+                parse("DDNS_RECORDS", record_type, Kind::RecordType)?,
+            );
+            record.insert("name".to_string(), Value::from(name.trim()));
+            records.push(Value::Object(record));
+        }
+    }
+
+    // DDNS_RECORDS_<下标>_<字段>，下标包括上面追加的记录，等于记录数时再追加一条新记录
+    let mut indexed: BTreeMap<usize, Vec<(&str, Value)>> = BTreeMap::new();
+    for (name, raw) in env::vars_os() {
+        let (Some(name), Some(raw)) = (name.to_str(), raw.to_str()) else {
+            continue;
+        };
+        let Some(rest) = name.strip_prefix(RECORD_PREFIX) else {
+            continue;
+        };
+        let field = rest.split_once('_').and_then(|(index, suffix)| {
+            let index = index.parse::<usize>().ok()?;
+            RECORD_VARS
+                .iter()
+                .find(|(var_suffix, _, _)| *var_suffix == suffix)
+                .map(|&(_, field, kind)| (index, field, kind))
+        });
+        match field {
+            Some((index, field, kind)) => indexed
+                .entry(index)
+                .or_default()
+                .push((field, parse(name, raw, kind)?)),
+            None => return Err(Error::Config(i18n::env_unknown(name))),
+        }
+    }
+    for (index, fields) in indexed {
+        if index > records.len() {
+            return Err(Error::Config(i18n::env_index_gap(index, records.len())));
+        }
+        if index == records.len() {
+            records.push(Value::Object(Map::new()));
+        }
+        let Value::Object(record) = &mut records[index] else {
+            return Err(Error::Config(i18n::config_not_table()));
+        };
+        for (field, value) in fields {
+            record.insert(field.to_string(), value);
+        }
+    }
+
+    Ok(first_new)
+}
+
+/// 在展开 accounts / zones 之后补全记录，区域与 account 中的值优先：
+/// 新增的记录缺少的 ttl 与 proxied 使用 DDNS_TTL、DDNS_PROXIED，
+/// 仍然没有 token 的记录使用 CF_API_TOKEN 或 CF_API_TOKEN_FILE
+pub fn fill(config: &mut Value, first_new: usize) -> Result<(), Error> {
+    let Some(Value::Array(records)) = config.get_mut("dns_records") else {
+        return Ok(());
+    };
+
+    let ttl = match var("DDNS_TTL") {
+        Some(ttl) => parse("DDNS_TTL", &ttl, Kind::Integer)?,
+        None => Value::from(1),
+    };
+    let proxied = match var("DDNS_PROXIED") {
+        Some(proxied) => parse("DDNS_PROXIED", &proxied, Kind::Bool)?,
+        None => Value::from(false),
+    };
+    for record in records.iter_mut().skip(first_new).filter_map(Value::as_object_mut) {
+        record.entry("ttl").or_insert_with(|| ttl.clone());
+        record.entry("proxied").or_insert_with(|| proxied.clone());
+    }
+
+    let fallback = match (var("CF_API_TOKEN"), var("CF_API_TOKEN_FILE")) {
+        (Some(token), _) => Some(("api_token", token)),
+        (None, Some(file)) => Some(("api_token_file", file)),
+        (None, None) => None,
+    };
+    if let Some((field, value)) = fallback {
+        records
+            .iter_mut()
+            .filter_map(Value::as_object_mut)
+            .filter(|record| {
+                ["api_token", "api_token_file", "api_token_env"]
+                    .iter()
+                    .all(|key| !record.contains_key(*key))
+            })
+            .for_each(|record| {
+                record.insert(field.to_string(), Value::from(value.as_str()));
+            });
+    }
+    Ok(())
+}
@@ -1,14 +1,34 @@
-use crate::obj::DATA_DIR;
+use crate::error::Error;
+use crate::i18n::{self, Lang};
+use crate::initialize::sources::SourceConfig;
+use crate::initialize::{accounts, env_conf, secrets};
+use crate::obj::{ARGS, DATA_DIR};
+use crate::secret::Secret;
+use chrono::NaiveTime;
+use ipnet::{Ipv4Net, Ipv6Net};
+use flexi_logger::LogSpecification;
 use log::{debug, error, trace};
 use regex::{Regex, RegexBuilder};
-use std::sync::{LazyLock, OnceLock};
+use std::path::{Path, PathBuf};
+use parking_lot::RwLock;
+use std::sync::{Arc, LazyLock, OnceLock};
 use std::{fs, io};
 
-pub static CONFIG: OnceLock<Config> = OnceLock::new();
+/// 当前生效的配置，重新加载时整体替换，正在进行的一轮更新继续使用旧的 Arc
+static CONFIG: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+/// 实际读取的配置文件
+static CONFIG_PATH: RwLock<Option<PathBuf>> = RwLock::new(None);
+/// 读取配置时发现的问题，日志初始化后再输出
+pub static CONFIG_WARNINGS: OnceLock<Vec<String>> = OnceLock::new();
 
-#[derive(Debug, serde::Deserialize, Clone, Copy, PartialEq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(
+    Debug, serde::Deserialize, serde::Serialize, clap::ValueEnum, Clone, Copy, PartialEq, Eq, Hash,
+)]
 pub enum RecordType {
+    #[value(name = "A")]
     A,
+    #[value(name = "AAAA")]
     AAAA,
 }
 impl RecordType {
@@ -43,10 +63,104 @@ impl RecordType {
     }
 }
 
-#[derive(Debug, serde::Deserialize, Clone)]
+/// 不自动更新的时间段（本地时间），格式为 "HH:MM-HH:MM"，结束早于开始时跨越午夜
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+#[serde(try_from = "String", into = "String")]
+pub struct QuietWindow {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+impl QuietWindow {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+}
+impl TryFrom<String> for QuietWindow {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parse = |time: &str| NaiveTime::parse_from_str(time.trim(), "%H:%M").ok();
+        match value.split_once('-').map(|(start, end)| (parse(start), parse(end))) {
+            Some((Some(start), Some(end))) if start != end => Ok(QuietWindow { start, end }),
+            _ => Err(i18n::quiet_window_invalid(&value)),
+        }
+    }
+}
+impl From<QuietWindow> for String {
+    fn from(window: QuietWindow) -> String {
+        format!("{}-{}", window.start.format("%H:%M"), window.end.format("%H:%M"))
+    }
+}
+
+/// 新检测到的地址需要保持多久才推送：连续检测到的次数，或持续的秒数
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Copy, PartialEq)]
+#[serde(try_from = "StableForRaw", into = "StableForRaw")]
+pub enum StableFor {
+    Checks(u32),
+    Seconds(u64),
+}
+impl Default for StableFor {
+    /// 检测到一次即推送
+    fn default() -> Self {
+        StableFor::Checks(1)
+    }
+}
+
+/// 配置中的写法：整数表示次数，字符串可以带 s、m、h 后缀表示时长
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum StableForRaw {
+    Checks(u32),
+    Text(String),
+}
+impl TryFrom<StableForRaw> for StableFor {
+    type Error = String;
+
+    fn try_from(raw: StableForRaw) -> Result<Self, Self::Error> {
+        let text = match raw {
+            StableForRaw::Checks(checks) if checks > 0 => return Ok(StableFor::Checks(checks)),
+            StableForRaw::Checks(checks) => checks.to_string(),
+            StableForRaw::Text(text) => text,
+        };
+        let trimmed = text.trim();
+        let (number, unit) = trimmed
+            .find(|c: char| !c.is_ascii_digit())
+            .map_or((trimmed, ""), |i| trimmed.split_at(i));
+        let seconds = |seconds: Option<u64>| {
+            seconds
+                .filter(|seconds| *seconds <= MAX_INTERVAL)
+                .map(StableFor::Seconds)
+        };
+        match (number.parse::<u64>(), unit.trim()) {
+            (Ok(checks @ 1..), "") => u32::try_from(checks).map(StableFor::Checks).ok(),
+            (Ok(secs), "s") => seconds(Some(secs)),
+            (Ok(minutes), "m") => seconds(minutes.checked_mul(60)),
+            (Ok(hours), "h") => seconds(hours.checked_mul(3600)),
+            _ => None,
+        }
+        .ok_or_else(|| i18n::stable_for_invalid(&text))
+    }
+}
+impl From<StableFor> for StableForRaw {
+    fn from(stable_for: StableFor) -> StableForRaw {
+        match stable_for {
+            StableFor::Checks(checks) => StableForRaw::Checks(checks),
+            StableFor::Seconds(seconds) => StableForRaw::Text(format!("{seconds}s")),
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
 pub struct DnsRecord {
-    pub api_token: String,
+    pub api_token: Secret,
+    /// 留空时按 name 通过 API 查找
+    #[serde(default)]
     pub zone_id: String,
+    #[serde(default)]
     pub dns_id: String,
     #[serde(rename = "type")]
     pub record_type: RecordType,
@@ -55,73 +169,332 @@ pub struct DnsRecord {
     pub proxied: bool,
 }
 
-fn get_default_delay() -> u64 {
+/// delay、retry_delay、max_retry_delay 与 force_refresh 的上限（一年），加到当前时间上不会溢出
+pub const MAX_INTERVAL: u64 = 365 * 24 * 3600;
+
+pub fn get_default_delay() -> u64 {
     60
 }
-fn get_default_mutli_thread() -> bool {
+pub fn get_default_retry_delay() -> u64 {
+    10
+}
+pub fn get_default_max_retry_delay() -> u64 {
+    3600
+}
+pub fn get_default_jitter() -> u8 {
+    10
+}
+pub fn get_default_mutli_thread() -> bool {
     false
 }
-fn get_default_log_level() -> String {
+pub fn get_default_watch_network() -> bool {
+    true
+}
+pub fn get_default_network_debounce() -> u64 {
+    5
+}
+pub fn get_default_log_level() -> String {
     if cfg!(debug_assertions) {
         "debug".to_string()
     } else {
         "info".to_string()
     }
 }
-fn get_default_ipv4_url() -> url::Url {
+pub fn get_default_ipv4_url() -> url::Url {
     url::Url::parse("https://ipv4.icanhazip.com/").unwrap()
 }
-fn get_default_ipv6_url() -> url::Url {
+pub fn get_default_ipv6_url() -> url::Url {
     url::Url::parse("https://ipv6.icanhazip.com/").unwrap()
 }
 
-#[derive(Debug, serde::Deserialize, Clone)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
 pub struct Config {
+    /// 一轮全部成功后等待的秒数
     #[serde(default = "get_default_delay")]
     pub delay: u64,
+    /// 失败后第一次重试前等待的秒数，之后每次失败翻倍
+    #[serde(default = "get_default_retry_delay")]
+    pub retry_delay: u64,
+    /// 重试等待时间的上限
+    #[serde(default = "get_default_max_retry_delay")]
+    pub max_retry_delay: u64,
+    /// 等待时间随机浮动的百分比，避免多个实例同时请求
+    #[serde(default = "get_default_jitter")]
+    pub jitter: u8,
+    /// cron 表达式（本地时间），设置后代替 delay 决定每轮开始的时间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<croner::Cron>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quiet_hours: Vec<QuietWindow>,
+    /// 每隔多少秒无论 IP 是否变化都重新推送所有记录，0 表示关闭
+    #[serde(default)]
+    pub force_refresh: u64,
+    #[serde(default)]
+    pub stable_for: StableFor,
+    /// 允许的范围，优先于内置的保留地址列表
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv4_allow: Vec<Ipv4Net>,
+    /// 额外拒绝的范围，优先于 ipv4_allow
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv4_deny: Vec<Ipv4Net>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv6_allow: Vec<Ipv6Net>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv6_deny: Vec<Ipv6Net>,
     #[serde(default = "get_default_mutli_thread")]
     pub mutli_thread: bool,
+    /// Linux 上公网地址或默认路由变化时不等待 delay，立即开始新一轮
+    #[serde(default = "get_default_watch_network")]
+    pub watch_network: bool,
+    /// 网络变化后等待稳定的秒数，期间的多次变化只触发一轮
+    #[serde(default = "get_default_network_debounce")]
+    pub network_debounce: u64,
     #[serde(default = "get_default_log_level")]
     pub log_level: String,
     #[serde(default = "get_default_ipv4_url")]
     pub ipv4_url: url::Url,
     #[serde(default = "get_default_ipv6_url")]
     pub ipv6_url: url::Url,
+    /// 按顺序尝试的 IPv4 来源，设置后代替 ipv4_url
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv4_sources: Vec<SourceConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ipv6_sources: Vec<SourceConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lang: Option<Lang>,
     pub dns_records: Vec<DnsRecord>,
 }
 
+pub struct Problem {
+    /// 对应 dns_records 的下标，None 表示全局字段
+    pub record: Option<usize>,
+    pub message: String,
+}
+
+fn is_valid_name(name: &str) -> bool {
+    let name = name.strip_suffix('.').unwrap_or(name);
+    name.len() <= 253
+        && name.split('.').count() >= 2
+        && name.split('.').enumerate().all(|(i, label)| {
+            (i == 0 && label == "*")
+                || (!label.is_empty()
+                    && label.len() <= 63
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        })
+}
+
+fn is_valid_id(id: &str) -> bool {
+    id.len() == 32 && id.chars().all(|c| c.is_ascii_hexdigit())
+}
+
 enum ConfigFile {
     Json(fs::File),
     Toml(Vec<u8>),
 }
 impl ConfigFile {
-    fn parse(self) -> Result<Config, String> {
+    /// 扩展名为 .json 时按 JSON 解析，其余按 TOML 解析
+    fn open(path: &Path) -> Result<ConfigFile, Error> {
+        let read_error = |e| Error::Config(i18n::config_read_failed(path.display(), e));
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            fs::File::open(path).map(ConfigFile::Json).map_err(read_error)
+        } else {
+            fs::read(path).map(ConfigFile::Toml).map_err(read_error)
+        }
+    }
+
+    /// 先解析为通用的值，以便合并环境变量后再转换为 Config
+    fn parse(self, path: &Path) -> Result<serde_json::Value, Error> {
+        let file = path.display();
         match self {
             ConfigFile::Json(f) => serde_json::from_reader(io::BufReader::new(f))
-                .map_err(|e| format!("config.json 格式不正确 | {}", e)),
-            ConfigFile::Toml(f) => toml::from_slice::<Config>(&f)
-                .map_err(|e| format!("config.toml 格式不正确 | {}", e)),
+                .map_err(|e| Error::Config(i18n::config_invalid(file, e))),
+            ConfigFile::Toml(f) => toml::from_slice::<serde_json::Value>(&f)
+                .map_err(|e| Error::Config(i18n::config_invalid(file, e))),
         }
     }
 }
 
-impl Config {
-    pub fn init() -> Result<(), String> {
-        let config_file = if DATA_DIR.join("config.toml").exists() {
-            trace!("找到 config.toml");
-            ConfigFile::Toml(fs::read(DATA_DIR.join("config.toml")).unwrap())
-        } else if DATA_DIR.join("config.json").exists() {
-            trace!("找到 config.json");
-            ConfigFile::Json(fs::File::open(DATA_DIR.join("config.json")).unwrap())
+/// 未指定 --config 时依次查找的配置文件
+pub fn config_candidates() -> Vec<PathBuf> {
+    #[allow(unused_mut)]
+    let mut dirs = vec![DATA_DIR.clone()];
+    #[cfg(unix)]
+    {
+        dirs.extend(crate::obj::xdg_dir("XDG_CONFIG_HOME", ".config"));
+        dirs.push(PathBuf::from("/etc/ddns_rust"));
+    }
+    dirs.iter()
+        .flat_map(|dir| [dir.join("config.toml"), dir.join("config.json")])
+        .collect()
+}
+
+/// --config（或 DDNS_CONFIG）指定的文件，否则为第一个存在的候选文件
+pub fn find_config() -> Result<PathBuf, Error> {
+    if let Some(path) = &ARGS.config {
+        return if path.is_file() {
+            Ok(path.clone())
         } else {
-            error!("找不到 config.toml 或 config.json");
-            return Err("找不到 config.toml 或 config.json".to_string());
+            Err(Error::Config(i18n::config_file_missing(path.display())))
+        };
+    }
+    let candidates = config_candidates();
+    candidates
+        .iter()
+        .find(|path| path.is_file())
+        .cloned()
+        .ok_or_else(|| {
+            let searched: Vec<String> = candidates
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            Error::Config(i18n::config_not_found(searched.join(", ")))
+        })
+}
+
+impl Config {
+    /// 检查反序列化无法覆盖的字段约束，不访问网络
+    pub fn validate(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let mut global = |message| {
+            problems.push(Problem {
+                record: None,
+                message,
+            })
+        };
+        if self.delay == 0 {
+            global(i18n::delay_zero());
+        }
+        if self.retry_delay == 0 {
+            global(i18n::retry_delay_zero());
+        }
+        if self.max_retry_delay < self.retry_delay {
+            global(i18n::max_retry_delay_too_small());
+        }
+        for (field, seconds) in [
+            ("delay", self.delay),
+            ("retry_delay", self.retry_delay),
+            ("max_retry_delay", self.max_retry_delay),
+            ("force_refresh", self.force_refresh),
+        ] {
+            if seconds > MAX_INTERVAL {
+                global(i18n::interval_too_large(field, seconds, MAX_INTERVAL));
+            }
+        }
+        if self.jitter > 100 {
+            global(i18n::jitter_invalid(self.jitter));
+        }
+        if let Err(e) = LogSpecification::parse(&self.log_level) {
+            global(i18n::log_level_invalid(e));
+        }
+        if self.dns_records.is_empty() {
+            global(i18n::no_records_configured());
+        }
+
+        for (i, record) in self.dns_records.iter().enumerate() {
+            let mut push = |message| {
+                problems.push(Problem {
+                    record: Some(i),
+                    message,
+                })
+            };
+            if record.api_token.expose().trim().is_empty() {
+                push(i18n::token_empty());
+            }
+            if !record.zone_id.is_empty() && !is_valid_id(&record.zone_id) {
+                push(i18n::id_invalid("zone_id", &record.zone_id));
+            }
+            if !record.dns_id.is_empty() && !is_valid_id(&record.dns_id) {
+                push(i18n::id_invalid("dns_id", &record.dns_id));
+            }
+            if record.ttl != 1 && !(60..=86400).contains(&record.ttl) {
+                push(i18n::ttl_invalid(record.ttl));
+            }
+            if !is_valid_name(&record.name) {
+                push(i18n::name_invalid(&record.name));
+            }
+            if let Some(j) = self.dns_records[..i].iter().position(|other| {
+                other.record_type == record.record_type
+                    && other.name.eq_ignore_ascii_case(&record.name)
+            }) {
+                push(i18n::duplicate_record(j));
+            }
+            if let Some(j) = self.dns_records[..i]
+                .iter()
+                .position(|other| !record.dns_id.is_empty() && other.dns_id == record.dns_id)
+            {
+                push(i18n::duplicate_dns_id(j));
+            }
+        }
+        problems
+    }
+
+    /// 读取配置文件并合并环境变量；未指定 --config 且环境变量中定义了记录时，可以没有配置文件
+    fn load() -> Result<(Config, Option<PathBuf>, Vec<String>), Error> {
+        let mut warnings = Vec::new();
+        let (mut value, path) = match find_config() {
+            Ok(path) => {
+                trace!("{}", i18n::config_found(path.display()));
+                let mut value = ConfigFile::open(&path)?.parse(&path)?;
+                if secrets::has_literal_token(&value) {
+                    warnings.extend(secrets::world_readable_warning(&path));
+                }
+                secrets::interpolate(&mut value)?;
+                (value, Some(path))
+            }
+            Err(_) if ARGS.config.is_none() && env_conf::has_records() => {
+                (serde_json::Value::Object(serde_json::Map::new()), None)
+            }
+            Err(e) => return Err(e),
         };
+        let first_new = env_conf::apply(&mut value)?;
+        accounts::expand(&mut value)?;
+        env_conf::fill(&mut value, first_new)?;
+        secrets::resolve_tokens(&mut value, path.as_deref().and_then(Path::parent))?;
+
+        let config = serde_json::from_value::<Config>(value).map_err(|e| {
+            Error::Config(match &path {
+                Some(path) => i18n::config_invalid(path.display(), e),
+                None => i18n::config_invalid(i18n::config_env(), e),
+            })
+        })?;
+        Ok((config, path, warnings))
+    }
+
+    /// 替换当前生效的配置
+    pub fn swap(config: Config, path: Option<PathBuf>) {
+        if let Some(lang) = config.lang {
+            i18n::set_lang(lang);
+        }
+        *CONFIG.write() = Some(Arc::new(config));
+        *CONFIG_PATH.write() = path;
+    }
+
+    /// 读取配置并完整校验，与重新加载时的检查相同
+    pub fn init() -> Result<(), Error> {
+        Config::init_with(true)
+    }
 
-        match config_file.parse() {
-            Ok(config) => {
-                debug!("成功解析配置文件");
-                CONFIG.set(config).expect("Config should only be set once");
+    /// 只读取配置不校验，供 config check 逐条输出问题、records list 查找 ID 使用
+    pub fn init_unvalidated() -> Result<(), Error> {
+        Config::init_with(false)
+    }
+
+    fn init_with(validate: bool) -> Result<(), Error> {
+        let loaded = Config::load().and_then(|(config, path, warnings)| {
+            if validate {
+                config.check(i18n::config_validation_failed)?;
+            }
+            Ok((config, path, warnings))
+        });
+        match loaded {
+            Ok((config, path, warnings)) => {
+                CONFIG_WARNINGS.set(warnings).expect("Config should only be set once");
+                debug!("{}", i18n::config_parsed());
+                Config::swap(config, path);
             }
             Err(e) => {
                 error!("{}", e);
@@ -130,4 +503,59 @@ impl Config {
         }
         Ok(())
     }
+
+    /// 把 validate 发现的问题合并为一个错误
+    fn check(&self, message: fn(String) -> String) -> Result<(), Error> {
+        let problems = self.validate();
+        if problems.is_empty() {
+            return Ok(());
+        }
+        let messages: Vec<String> = problems
+            .into_iter()
+            .map(|problem| match problem.record {
+                Some(i) => format!("#{i} {}", problem.message),
+                None => problem.message,
+            })
+            .collect();
+        Err(Error::Config(message(messages.join("; "))))
+    }
+
+    /// 重新读取并完整校验配置，全部通过后才替换当前配置，失败时保留旧配置
+    ///
+    /// 返回读取时的警告，以及需要重启才能生效的修改，由调用方记录日志
+    pub fn reload() -> Result<Vec<String>, Error> {
+        let (config, path, mut warnings) = Config::load()?;
+        config.check(i18n::reload_invalid)?;
+        if let Ok(current) = Config::current() {
+            let changed = [
+                ("log_level", current.log_level != config.log_level),
+                ("mutli_thread", current.mutli_thread != config.mutli_thread),
+                ("watch_network", current.watch_network != config.watch_network),
+            ];
+            warnings.extend(
+                changed
+                    .into_iter()
+                    .filter(|(_, changed)| *changed)
+                    .map(|(field, _)| i18n::reload_needs_restart(field)),
+            );
+        }
+        Config::swap(config, path);
+        Ok(warnings)
+    }
+
+    /// 当前生效的配置
+    pub fn current() -> Result<Arc<Config>, Error> {
+        CONFIG
+            .read()
+            .clone()
+            .ok_or_else(|| Error::Runtime(i18n::config_uninitialized()))
+    }
+
+    /// 描述配置来源，用于日志与 config check
+    pub fn source() -> String {
+        match CONFIG_PATH.read().as_ref() {
+            Some(path) => i18n::config_loaded(path.display()),
+            None => i18n::config_from_env(),
+        }
+    }
 }
@@ -1,4 +1,6 @@
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+
+use crate::initialize::load_conf::RecordType;
 
 #[derive(Parser)]
 #[command(name = "Cloudflare DDNS")]
@@ -6,6 +8,14 @@ use clap::{Parser, Subcommand};
 pub struct CliArgs {
     #[command(subcommand)]
     pub command: Commands,
+
+    /// data path for logs and state, default is <current execute>/data if it exists
+    #[arg(long, global = true)]
+    pub datadir: Option<std::path::PathBuf>,
+
+    /// configuration file, default is searched in the data path and standard locations
+    #[arg(long, global = true, env = "DDNS_CONFIG")]
+    pub config: Option<std::path::PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -16,10 +26,65 @@ pub enum Commands {
         #[arg(long)]
         loops: bool,
 
-        /// data path, default is <current execute>/data
+        /// Print the planned changes without updating any DNS record
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Update the selected records once
+    Update {
+        /// Push the current address even if it has not changed
+        #[arg(long)]
+        force: bool,
+
+        /// Only update records with this name
         #[arg(long)]
-        datadir: Option<std::path::PathBuf>,
+        record: Option<String>,
+
+        /// Only update records of this type
+        #[arg(long = "type", value_enum, ignore_case = true)]
+        record_type: Option<RecordType>,
+    },
+    /// Manage the configuration file
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Create a configuration file interactively
+    Init,
+    /// Discover zones and DNS records
+    Records {
+        #[command(subcommand)]
+        action: RecordsAction,
+    },
+    /// Show the last detected addresses and record updates
+    Status {
+        /// Print the state as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Detect the public addresses without contacting Cloudflare
+    Ip {
+        /// Only detect the IPv4 address
+        #[arg(short = '4', long)]
+        ipv4: bool,
+
+        /// Only detect the IPv6 address
+        #[arg(short = '6', long)]
+        ipv6: bool,
+    },
+    /// Control the running --loops instance (Unix-like systems only)
+    #[cfg(unix)]
+    Ctl {
+        #[arg(value_enum)]
+        action: CtlAction,
+    },
+    /// Print the shell completion script
+    Completions {
+        #[arg(value_enum)]
+        shell: clap_complete::Shell,
     },
+    /// Print the man page in roff format
+    Manpage,
     /// Install components
     Install {
         #[command(subcommand)]
@@ -32,6 +97,51 @@ pub enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Validate the configuration file
+    Check {
+        /// Also verify tokens, zones and records against the Cloudflare API
+        #[arg(long)]
+        online: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum RecordsAction {
+    /// List accessible zones and their A/AAAA records
+    List {
+        /// API token to use, default is every token in the configuration file
+        #[arg(long, env = "CF_API_TOKEN", hide_env_values = true)]
+        token: Option<String>,
+
+        /// Print ready-to-paste dns_records entries instead of a table
+        #[arg(long, value_enum)]
+        emit: Option<EmitFormat>,
+    },
+}
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum EmitFormat {
+    Toml,
+    Json,
+}
+
+#[cfg(unix)]
+#[derive(ValueEnum, Clone, Copy)]
+pub enum CtlAction {
+    /// Stop updating until resumed
+    Pause,
+    /// Resume updating
+    Resume,
+    /// Start an update round now
+    Trigger,
+    /// Show whether the instance is paused and when it runs next
+    Status,
+    /// Reload the configuration file
+    Reload,
+}
+
 #[derive(Subcommand)]
 pub enum InstallComponents {
     /// Install as a system service (Windows service or systemd service)
@@ -41,6 +151,12 @@ pub enum InstallComponents {
     /// Install as a cron job (Unix-like systems only)
     #[cfg(unix)]
     Cron,
+    /// Install bash, zsh and fish completions (Unix-like systems only)
+    #[cfg(unix)]
+    Completions,
+    /// Install the man page (Unix-like systems only)
+    #[cfg(unix)]
+    Manpage,
 }
 
 #[derive(Subcommand)]
@@ -52,4 +168,10 @@ pub enum UninstallComponents {
     /// Uninstall the cron job (Unix-like systems only)
     #[cfg(unix)]
     Cron,
+    /// Uninstall the shell completions (Unix-like systems only)
+    #[cfg(unix)]
+    Completions,
+    /// Uninstall the man page (Unix-like systems only)
+    #[cfg(unix)]
+    Manpage,
 }
@@ -0,0 +1,114 @@
+use serde_json::Value;
+use std::env;
+use std::fs;
+use std::path::Path;
+
+use crate::error::Error;
+use crate::i18n;
+
+/// 替换字符串中的 ${NAME}，$$ 表示字面量 $
+fn interpolate_str(text: &str) -> Result<String, Error> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        if let Some(after) = after.strip_prefix('$') {
+            result.push('$');
+            rest = after;
+        } else if let Some(after) = after.strip_prefix('{') {
+            let end = after
+                .find('}')
+                .ok_or_else(|| Error::Config(i18n::interpolation_unclosed(text)))?;
+            let name = &after[..end];
+            let value = env::var(name)
+                .map_err(|_| Error::Config(i18n::interpolation_missing(name)))?;
+            result.push_str(&value);
+            rest = &after[end + 1..];
+        } else {
+            result.push('$');
+            rest = after;
+        }
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// 对配置中所有字符串字段做 ${ENV} 替换
+pub fn interpolate(value: &mut Value) -> Result<(), Error> {
+    match value {
+        Value::String(text) if text.contains('$') => *text = interpolate_str(text)?,
+        Value::Array(items) => items.iter_mut().try_for_each(interpolate)?,
+        Value::Object(map) => map.values_mut().try_for_each(interpolate)?,
+        _ => (),
+    }
+    Ok(())
+}
+
+/// 配置文件中是否直接写有 token（而不是 ${ENV}、文件或环境变量）
+pub fn has_literal_token(value: &Value) -> bool {
+    let is_literal = |holder: &Value| {
+        holder["api_token"]
+            .as_str()
+            .is_some_and(|token| !token.is_empty() && !token.contains("${"))
+    };
+    value["dns_records"]
+        .as_array()
+        .is_some_and(|records| records.iter().any(is_literal))
+        || value["accounts"]
+            .as_object()
+            .is_some_and(|accounts| accounts.values().any(is_literal))
+}
+
+/// 其他用户可以读取该文件时返回警告
+#[cfg(unix)]
+pub fn world_readable_warning(path: &Path) -> Option<String> {
+    use std::os::unix::fs::PermissionsExt;
+    let mode = fs::metadata(path).ok()?.permissions().mode();
+    (mode & 0o004 != 0).then(|| i18n::config_world_readable(path.display(), mode & 0o777))
+}
+
+#[cfg(not(unix))]
+pub fn world_readable_warning(_path: &Path) -> Option<String> {
+    None
+}
+
+/// 把 api_token_file 与 api_token_env 读取为 api_token，相对路径以配置文件所在目录为基准
+pub fn resolve_tokens(value: &mut Value, base_dir: Option<&Path>) -> Result<(), Error> {
+    let Some(records) = value["dns_records"].as_array_mut() else {
+        return Ok(());
+    };
+    for (i, record) in records.iter_mut().enumerate() {
+        let Some(record) = record.as_object_mut() else {
+            continue;
+        };
+        let file = record.remove("api_token_file");
+        let var = record.remove("api_token_env");
+        let sources = [record.contains_key("api_token"), file.is_some(), var.is_some()];
+        if sources.iter().filter(|&&set| set).count() > 1 {
+            return Err(Error::Config(i18n::token_source_conflict(i)));
+        }
+
+        let token = if let Some(file) = file {
+            let file = file
+                .as_str()
+                .ok_or_else(|| Error::Config(i18n::token_source_invalid(i, "api_token_file")))?;
+            let path = match base_dir {
+                Some(base_dir) => base_dir.join(file),
+                None => Path::new(file).to_path_buf(),
+            };
+            fs::read_to_string(&path)
+                .map_err(|e| Error::Config(i18n::token_file_read_failed(path.display(), e)))?
+        } else if let Some(var) = var {
+            let var = var
+                .as_str()
+                .ok_or_else(|| Error::Config(i18n::token_source_invalid(i, "api_token_env")))?;
+            env::var(var).map_err(|_| Error::Config(i18n::interpolation_missing(var)))?
+        } else {
+            continue;
+        };
+        // 文件末尾常带有换行
+        record.insert("api_token".to_string(), Value::from(token.trim()));
+    }
+    Ok(())
+}
@@ -0,0 +1,320 @@
+use regex::Regex;
+use std::fmt::{self, Display};
+
+use crate::i18n;
+
+/// 内置的常用 IP 来源，按地址族选择对应的地址
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Preset {
+    Ipify,
+    Cloudflare,
+    Icanhazip,
+    IpSb,
+    OpenDns,
+    CloudflareDns,
+}
+impl Preset {
+    const ALL: [Preset; 6] = [
+        Preset::Ipify,
+        Preset::Cloudflare,
+        Preset::Icanhazip,
+        Preset::IpSb,
+        Preset::OpenDns,
+        Preset::CloudflareDns,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Ipify => "ipify",
+            Preset::Cloudflare => "cloudflare",
+            Preset::Icanhazip => "icanhazip",
+            Preset::IpSb => "ip.sb",
+            Preset::OpenDns => "opendns",
+            Preset::CloudflareDns => "cloudflare-dns",
+        }
+    }
+}
+
+/// 从响应正文中提取地址的方式
+#[derive(Debug, Clone)]
+pub enum Parser {
+    /// 整个正文（去掉首尾空白）就是地址
+    Plain,
+    /// 名为 ip 的捕获组，None 时使用内置的地址格式并取第一个匹配
+    Regex(Option<Regex>),
+    /// JSON pointer 指向的字符串，指向数组时取第一个符合地址族的元素
+    Json(String),
+    /// key=value 行中指定 key 的值
+    KeyValue(String),
+}
+impl Display for Parser {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Parser::Plain => write!(f, "plain"),
+            Parser::Regex(None) => write!(f, "regex"),
+            Parser::Regex(Some(pattern)) => write!(f, "regex {}", pattern.as_str()),
+            Parser::Json(pointer) => write!(f, "json {pointer}"),
+            Parser::KeyValue(key) => write!(f, "kv {key}"),
+        }
+    }
+}
+
+/// DNS 来源查询的记录类型
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DnsType {
+    A,
+    AAAA,
+    TXT,
+}
+impl DnsType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DnsType::A => "A",
+            DnsType::AAAA => "AAAA",
+            DnsType::TXT => "TXT",
+        }
+    }
+    pub fn code(&self) -> u16 {
+        match self {
+            DnsType::A => 1,
+            DnsType::AAAA => 28,
+            DnsType::TXT => 16,
+        }
+    }
+}
+
+/// DNS 来源查询的类别，whoami.cloudflare 需要 CH
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DnsClass {
+    IN,
+    CH,
+}
+impl DnsClass {
+    pub fn name(&self) -> &'static str {
+        match self {
+            DnsClass::IN => "IN",
+            DnsClass::CH => "CH",
+        }
+    }
+    pub fn code(&self) -> u16 {
+        match self {
+            DnsClass::IN => 1,
+            DnsClass::CH => 3,
+        }
+    }
+}
+
+/// ipv4_sources 与 ipv6_sources 中的一项
+#[derive(Debug, Clone, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "SourceRaw", into = "SourceRaw")]
+pub enum SourceConfig {
+    Preset(Preset),
+    Http {
+        url: url::Url,
+        parser: Parser,
+    },
+    /// resolver 为地址或主机名，可以带端口；record_type 为 None 时按地址族查询 A 或 AAAA
+    Dns {
+        name: String,
+        resolver: String,
+        record_type: Option<DnsType>,
+        class: DnsClass,
+    },
+    /// server 为地址或主机名，可以带端口
+    Stun {
+        server: String,
+    },
+}
+
+/// 配置中的写法：预设名称、URL 或 stun: 字符串，或者带解析方式的表，或者 DNS 查询
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+enum SourceRaw {
+    Name(String),
+    Http {
+        url: url::Url,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        parser: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pattern: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        pointer: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        key: Option<String>,
+    },
+    Dns {
+        dns: String,
+        resolver: String,
+        #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+        record_type: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        class: Option<String>,
+    },
+}
+
+impl TryFrom<SourceRaw> for SourceConfig {
+    type Error = String;
+
+    fn try_from(raw: SourceRaw) -> Result<Self, Self::Error> {
+        let (url, parser, pattern, pointer, key) = match raw {
+            SourceRaw::Name(name) => {
+                if let Some(preset) = Preset::ALL.into_iter().find(|preset| preset.name() == name) {
+                    return Ok(SourceConfig::Preset(preset));
+                }
+                // RFC 7064 的 stun:host[:port]
+                if let Some(server) = name.strip_prefix("stun:") {
+                    if server.is_empty() {
+                        return Err(i18n::source_stun_empty());
+                    }
+                    return Ok(SourceConfig::Stun {
+                        server: server.to_string(),
+                    });
+                }
+                let presets: Vec<&str> = Preset::ALL.iter().map(Preset::name).collect();
+                return url::Url::parse(&name)
+                    .map(|url| SourceConfig::Http {
+                        url,
+                        parser: Parser::Regex(None),
+                    })
+                    .map_err(|_| i18n::source_invalid(&name, presets.join(", ")));
+            }
+            SourceRaw::Http {
+                url,
+                parser,
+                pattern,
+                pointer,
+                key,
+            } => (url, parser, pattern, pointer, key),
+            SourceRaw::Dns {
+                dns,
+                resolver,
+                record_type,
+                class,
+            } => {
+                let labels: Vec<&str> = dns.trim_end_matches('.').split('.').collect();
+                if dns.len() > 253 || labels.iter().any(|l| l.is_empty() || l.len() > 63) {
+                    return Err(i18n::source_dns_name_invalid(&dns));
+                }
+                let record_type = record_type
+                    .map(|value| {
+                        [DnsType::A, DnsType::AAAA, DnsType::TXT]
+                            .into_iter()
+                            .find(|t| t.name().eq_ignore_ascii_case(&value))
+                            .ok_or_else(|| i18n::source_dns_type_unknown(&value))
+                    })
+                    .transpose()?;
+                let class = match class {
+                    Some(value) => [DnsClass::IN, DnsClass::CH]
+                        .into_iter()
+                        .find(|c| c.name().eq_ignore_ascii_case(&value))
+                        .ok_or_else(|| i18n::source_dns_class_unknown(&value))?,
+                    None => DnsClass::IN,
+                };
+                return Ok(SourceConfig::Dns {
+                    name: dns,
+                    resolver,
+                    record_type,
+                    class,
+                });
+            }
+        };
+        let parser = match parser.as_deref() {
+            Some("plain") => Parser::Plain,
+            None | Some("regex") => match pattern {
+                Some(pattern) => {
+                    let regex = Regex::new(&pattern)
+                        .map_err(|e| i18n::source_pattern_invalid(&pattern, e))?;
+                    if !regex.capture_names().any(|name| name == Some("ip")) {
+                        return Err(i18n::source_pattern_no_group(&pattern));
+                    }
+                    Parser::Regex(Some(regex))
+                }
+                None => Parser::Regex(None),
+            },
+            Some("json") => {
+                let pointer =
+                    pointer.ok_or_else(|| i18n::source_field_missing("json", "pointer"))?;
+                // 空字符串表示整个文档，其余必须以 / 开头，否则永远不会匹配
+                if !pointer.is_empty() && !pointer.starts_with('/') {
+                    return Err(i18n::source_pointer_invalid(&pointer));
+                }
+                Parser::Json(pointer)
+            }
+            Some("kv") => {
+                Parser::KeyValue(key.ok_or_else(|| i18n::source_field_missing("kv", "key"))?)
+            }
+            Some(parser) => return Err(i18n::source_parser_unknown(parser)),
+        };
+        Ok(SourceConfig::Http { url, parser })
+    }
+}
+
+impl From<SourceConfig> for SourceRaw {
+    fn from(source: SourceConfig) -> SourceRaw {
+        let (url, parser) = match source {
+            SourceConfig::Preset(preset) => return SourceRaw::Name(preset.name().to_string()),
+            // 默认的解析方式写回为 URL 字符串
+            SourceConfig::Http {
+                url,
+                parser: Parser::Regex(None),
+            } => return SourceRaw::Name(url.to_string()),
+            SourceConfig::Http { url, parser } => (url, parser),
+            SourceConfig::Stun { server } => return SourceRaw::Name(format!("stun:{server}")),
+            SourceConfig::Dns {
+                name,
+                resolver,
+                record_type,
+                class,
+            } => {
+                return SourceRaw::Dns {
+                    dns: name,
+                    resolver,
+                    record_type: record_type.map(|t| t.name().to_string()),
+                    class: (class != DnsClass::IN).then(|| class.name().to_string()),
+                };
+            }
+        };
+        let (name, pattern, pointer, key) = match parser {
+            Parser::Plain => ("plain", None, None, None),
+            Parser::Regex(regex) => (
+                "regex",
+                regex.map(|regex| regex.as_str().to_string()),
+                None,
+                None,
+            ),
+            Parser::Json(pointer) => ("json", None, Some(pointer), None),
+            Parser::KeyValue(key) => ("kv", None, None, Some(key)),
+        };
+        SourceRaw::Http {
+            url,
+            parser: Some(name.to_string()),
+            pattern,
+            pointer,
+            key,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn json(pointer: &str) -> Result<SourceConfig, String> {
+        SourceConfig::try_from(SourceRaw::Http {
+            url: url::Url::parse("https://example.com/myip.json").unwrap(),
+            parser: Some("json".to_string()),
+            pattern: None,
+            pointer: Some(pointer.to_string()),
+            key: None,
+        })
+    }
+
+    #[test]
+    fn json_pointer_must_start_with_slash() {
+        assert!(json("/data/ip").is_ok());
+        assert!(json("").is_ok());
+        assert_eq!(json("ip").unwrap_err(), i18n::source_pointer_invalid("ip"));
+    }
+}
@@ -1,8 +1,17 @@
+mod cloudflare;
+mod error;
+mod i18n;
 mod obj;
+mod secret;
 mod run;
 mod initialize;
 mod setup;
+mod state;
+mod tools;
 
+use std::process::ExitCode;
+
+use error::Error;
 use initialize::{load_conf, parse_args};
 use setup::{install, uninstall};
 
@@ -10,41 +19,125 @@ use setup::{install, uninstall};
 #[global_allocator]
 static GLOBAL: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-fn main() -> Result<(), String> {
-    match &*obj::ARGS { 
-        parse_args::Commands::Run { loops, datadir: _ } => {
+fn main() -> ExitCode {
+    match execute() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            e.exit_code()
+        }
+    }
+}
+
+/// 按配置初始化日志，并记录使用的配置文件
+fn start_logger() -> Result<flexi_logger::LoggerHandle, Error> {
+    let logger = obj::init_log(&load_conf::Config::current()?.log_level)?;
+    log::info!("{}", load_conf::Config::source());
+    load_conf::CONFIG_WARNINGS
+        .get()
+        .into_iter()
+        .flatten()
+        .for_each(|warning| log::warn!("{warning}"));
+    Ok(logger)
+}
+
+fn execute() -> Result<(), Error> {
+    match &obj::ARGS.command {
+        parse_args::Commands::Run { loops, dry_run } => {
             load_conf::Config::init()?;
-            let _logger = obj::init_log(
-                &load_conf::CONFIG
-                    .get()
-                    .ok_or("CONFIG_JSON 未初始化")?
-                    .log_level
-            )?;
+            let _logger = start_logger()?;
 
             #[cfg(windows)]
-            if *loops {
+            if *loops && !*dry_run {
                 match run::run_service_windows() {
                     Ok(_) => return Ok(()),
                     Err(e) => {
                         if e != "Use service control manager to start service" {
-                            return Err(e.to_string());
+                            return Err(Error::Runtime(e.to_string()));
                         }
                     }
                 }
             }
-            run::run(*loops)?;
+            let mode = if *dry_run {
+                run::UpdateMode::DryRun
+            } else {
+                run::UpdateMode::Normal
+            };
+            run::run(*loops, mode, |_| true)?;
         }
+        parse_args::Commands::Update {
+            force,
+            record,
+            record_type,
+        } => {
+            load_conf::Config::init()?;
+            let _logger = start_logger()?;
+
+            let mode = if *force {
+                run::UpdateMode::Force
+            } else {
+                run::UpdateMode::Normal
+            };
+            run::run(false, mode, |dns_record| {
+                record.as_ref().is_none_or(|name| {
+                    dns_record
+                        .name
+                        .trim_end_matches('.')
+                        .eq_ignore_ascii_case(name.trim_end_matches('.'))
+                }) && record_type.is_none_or(|t| dns_record.record_type == t)
+            })?;
+        }
+        parse_args::Commands::Config { action } => match action {
+            parse_args::ConfigAction::Check { online } => {
+                load_conf::Config::init_unvalidated()?;
+                tools::check::check(*online)?;
+            }
+        },
+        parse_args::Commands::Init => tools::wizard::init()?,
+        parse_args::Commands::Records { action } => match action {
+            parse_args::RecordsAction::List { token, emit } => {
+                let tokens = match token {
+                    Some(token) => vec![secret::Secret::new(token.as_str())],
+                    None => {
+                        load_conf::Config::init_unvalidated()?;
+                        load_conf::Config::current()?
+                            .dns_records
+                            .iter()
+                            .map(|record| record.api_token.clone())
+                            .collect()
+                    }
+                };
+                tools::records::list(tokens, *emit)?;
+            }
+        },
+        parse_args::Commands::Status { json } => tools::status::status(*json)?,
+        parse_args::Commands::Ip { ipv4, ipv6 } => {
+            load_conf::Config::init()?;
+            tools::ip::ip(*ipv4, *ipv6)?;
+        }
+        #[cfg(unix)]
+        parse_args::Commands::Ctl { action } => tools::ctl::ctl(*action)?,
+        parse_args::Commands::Completions { shell } => tools::generate::completions(*shell)?,
+        parse_args::Commands::Manpage => tools::generate::manpage()?,
         parse_args::Commands::Install { component } => match component {
             parse_args::InstallComponents::Service => install::service()?,
             parse_args::InstallComponents::Schedule => install::schedule()?,
             #[cfg(unix)]
             parse_args::InstallComponents::Cron => install::cron()?,
+            #[cfg(unix)]
+            parse_args::InstallComponents::Completions => install::completions()?,
+            #[cfg(unix)]
+            parse_args::InstallComponents::Manpage => install::manpage()?,
         },
         parse_args::Commands::Uninstall { component } => match component {
             parse_args::UninstallComponents::Service => uninstall::service()?,
             parse_args::UninstallComponents::Schedule => uninstall::schedule()?,
             #[cfg(unix)]
             parse_args::UninstallComponents::Cron => uninstall::cron()?,
+            #[cfg(unix)]
+            parse_args::UninstallComponents::Completions => uninstall::completions()?,
+            #[cfg(unix)]
+            parse_args::UninstallComponents::Manpage => uninstall::manpage()?,
         },
     }
     Ok(())
@@ -4,36 +4,83 @@ use flexi_logger::{
     colored_detailed_format, detailed_format,
 };
 use log::debug;
-use std::env::{current_dir, current_exe};
+use reqwest::{Client, ClientBuilder, retry, tls};
+use std::env::{self, current_dir, current_exe};
+use std::path::PathBuf;
 use std::sync::LazyLock;
+use std::time::Duration;
 
+use crate::error::Error;
+use crate::i18n;
 use crate::initialize::parse_args;
 
-pub static ARGS: LazyLock<parse_args::Commands> =
-    LazyLock::new(|| parse_args::CliArgs::parse().command);
+pub static ARGS: LazyLock<parse_args::CliArgs> = LazyLock::new(parse_args::CliArgs::parse);
 
-pub static DATA_DIR: LazyLock<std::path::PathBuf> = LazyLock::new(|| {
-    let parse_args::Commands::Run { loops: _, datadir } = &*ARGS else {
-        unreachable!()
-    };
+/// 二进制文件同目录（debug 下为工作目录）下的 data，用于便携部署
+fn local_data_dir() -> PathBuf {
+    if cfg!(debug_assertions) {
+        current_dir().unwrap_or_else(|e| panic!("{} | {e}", i18n::cannot_read_cwd()))
+    } else {
+        current_exe()
+            .unwrap_or_else(|e| panic!("{} | {e}", i18n::cannot_read_exe_path()))
+            .parent()
+            .unwrap_or_else(|| panic!("{}", i18n::cannot_read_exe_dir()))
+            .to_path_buf()
+    }
+    .join("data")
+}
+
+/// 读取 XDG 目录变量，未设置时使用 $HOME 下的默认位置
+#[cfg(unix)]
+pub fn xdg_dir(var: &str, default: &str) -> Option<PathBuf> {
+    env::var_os(var)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(default)))
+        .map(|dir| dir.join("ddns_rust"))
+}
 
-    datadir.clone().unwrap_or_else(|| {
-        if cfg!(debug_assertions) {
-            current_dir().expect("无法读取当前工作目录")
-        } else {
-            current_exe()
-                .expect("无法读取二进制文件路径")
-                .parent()
-                .expect("无法读取二进制文件所在目录")
-                .to_path_buf()
+/// 按 --datadir、便携目录、/var/lib/ddns_rust、$XDG_STATE_HOME 的顺序选择数据目录
+pub static DATA_DIR: LazyLock<PathBuf> = LazyLock::new(|| {
+    if let Some(datadir) = &ARGS.datadir {
+        return datadir.clone();
+    }
+    let local = local_data_dir();
+    if local.is_dir() {
+        return local;
+    }
+    #[cfg(unix)]
+    {
+        let system = PathBuf::from("/var/lib/ddns_rust");
+        if system.is_dir() {
+            return system;
         }
-        .join("data")
-    })
+        if let Some(user) = xdg_dir("XDG_STATE_HOME", ".local/state") {
+            return user;
+        }
+    }
+    local
+});
+
+pub static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    let time_out_secs = Duration::from_secs(5);
+    ClientBuilder::new()
+        .no_proxy()
+        .retry(retry::for_host("*").max_retries_per_request(3))
+        .https_only(true)
+        .http2_prior_knowledge()
+        .gzip(true)
+        .pool_idle_timeout(Duration::from_secs(180))
+        .connect_timeout(time_out_secs)
+        .read_timeout(time_out_secs)
+        .min_tls_version(tls::Version::TLS_1_3)
+        .build()
+        .unwrap()
 });
 
-pub fn init_log(log_level: &String) -> Result<LoggerHandle, String> {
+pub fn init_log(log_level: &String) -> Result<LoggerHandle, Error> {
     let logger = Logger::try_with_str(log_level)
-        .map_err(|e| format!("log 等级格式错误，参考 https://docs.rs/flexi_logger/latest/flexi_logger/struct.LogSpecification.html \n{e}"))?
+        .map_err(|e| Error::Config(i18n::log_level_invalid(e)))?
         .log_to_file(
             FileSpec::default()
                 .directory(DATA_DIR.join("logs")) //定义日志文件位置
@@ -53,7 +100,7 @@ pub fn init_log(log_level: &String) -> Result<LoggerHandle, String> {
         .write_mode(WriteMode::Async)
         .append() //指定日志文件为添加内容而不是覆盖重写
         .start()
-        .map_err(|e| format!("无法创建logger句柄,回溯错误:\n{e}"))?;
-    debug!("日志初始化成功");
+        .map_err(|e| Error::Runtime(i18n::logger_create_failed(e)))?;
+    debug!("{}", i18n::logger_initialized());
     Ok(logger)
 }
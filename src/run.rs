@@ -1,14 +1,31 @@
+use chrono::{DateTime, Utc};
 #[allow(unused_imports)]
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
+use parking_lot::Mutex;
 use std::sync::LazyLock;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tokio::sync::Notify;
 use tokio::sync::watch::{self, Receiver, Sender};
-use tokio::time::{Duration, sleep};
+use tokio::time::sleep;
 
 #[cfg(windows)]
 use windows_services::{Command, Service};
 
+use crate::cloudflare;
+use crate::error::Error;
+use crate::i18n;
 use crate::initialize::load_conf::{self, RecordType};
+pub use crate::run::update_ip::UpdateMode;
 use crate::run::update_ip::update_ip;
+use crate::state;
+mod backoff;
+#[cfg(unix)]
+pub mod control;
+pub mod detect;
+pub mod filter;
+#[cfg(target_os = "linux")]
+mod netwatch;
+mod schedule;
 mod update_ip;
 
 #[derive(PartialEq)]
@@ -21,35 +38,143 @@ enum SignalType {
 static LOOP_SIGNAL: LazyLock<(Sender<SignalType>, Receiver<SignalType>)> =
     LazyLock::new(|| watch::channel(SignalType::Run));
 
+/// 重新加载配置后，下一轮推送所有记录，使新增或修改的记录生效
+static REFRESH_NEXT: AtomicBool = AtomicBool::new(false);
+
+/// 唤醒循环立即开始新一轮
+static TRIGGER: Notify = Notify::const_new();
+
+/// 上一轮结束与下一轮预计开始的时间，供 ctl status 查询
+static LAST_ROUND: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+static NEXT_ROUND: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+/// 切换暂停与运行；已经收到退出信号时保持 Stop 并返回 false，否则循环可能永远不会退出
+fn set_paused(paused: bool) -> bool {
+    let signal = if paused {
+        SignalType::Pause
+    } else {
+        SignalType::Run
+    };
+    let mut stopping = false;
+    LOOP_SIGNAL.0.send_if_modified(|current| {
+        if *current == SignalType::Stop {
+            stopping = true;
+            return false;
+        }
+        let modified = *current != signal;
+        *current = signal;
+        modified
+    });
+    !stopping
+}
+
 fn system_signal_handler() {
-    debug!("退出中...");
+    debug!("{}", i18n::exiting());
     LOOP_SIGNAL.0.send(SignalType::Stop).unwrap();
 }
 
-pub fn run(loops_run: bool) -> Result<(), String> {
-    let conf_json = load_conf::CONFIG
-        .get()
-        .ok_or("运行run函数时，CONFIG_JSON 未初始化")?;
-
-    let ipv4_config: Vec<&load_conf::DnsRecord> = conf_json
-        .dns_records
-        .iter()
-        .filter(|&x| x.record_type == RecordType::A)
-        .collect();
-    let ipv6_config: Vec<&load_conf::DnsRecord> = conf_json
-        .dns_records
-        .iter()
-        .filter(|&x| x.record_type == RecordType::AAAA)
-        .collect();
+/// SIGTERM 与 Ctrl-C 一样退出，SIGHUP 请求重新加载配置
+#[cfg(unix)]
+fn listen_unix_signals() -> Result<(), Error> {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let listen = |kind| {
+        signal(kind).map_err(|e| {
+            let e = Error::Runtime(i18n::signal_handler_failed(e));
+            error!("{e}");
+            e
+        })
+    };
+    let mut terminate = listen(SignalKind::terminate())?;
+    let mut hangup = listen(SignalKind::hangup())?;
+    tokio::spawn(async move {
+        if terminate.recv().await.is_some() {
+            system_signal_handler();
+        }
+    });
+    tokio::spawn(async move {
+        while hangup.recv().await.is_some() {
+            info!("{}", i18n::reload_requested());
+            let _ = reload();
+        }
+    });
+    Ok(())
+}
+
+/// 校验通过才替换配置并立即开始新一轮，失败时继续使用旧配置
+fn reload() -> Result<(), Error> {
+    match load_conf::Config::reload() {
+        Ok(warnings) => {
+            warnings.iter().for_each(|warning| warn!("{warning}"));
+            backoff::reset();
+            cloudflare::clear_resolved();
+            // 保留已推送的地址，下一轮推送所有记录时仍然等待 stable_for
+            REFRESH_NEXT.store(true, Ordering::Relaxed);
+            info!("{}", i18n::reload_done(load_conf::Config::source()));
+            // 使新增的记录马上生效
+            TRIGGER.notify_one();
+            Ok(())
+        }
+        Err(e) => {
+            error!("{}", i18n::reload_failed(&e));
+            Err(e)
+        }
+    }
+}
 
+/// `select` 用于只更新部分记录，没有记录被选中时返回配置错误
+pub fn run(
+    loops_run: bool,
+    mode: UpdateMode,
+    select: impl Fn(&load_conf::DnsRecord) -> bool,
+) -> Result<(), Error> {
+    let conf_json = load_conf::Config::current()?;
+    if !conf_json.dns_records.iter().any(&select) {
+        return Err(Error::Config(i18n::no_record_selected()));
+    }
+
+    // 每轮都读取当前配置，重新加载后的下一轮即使用新配置
     let run_once = || async {
-        let _a = tokio::join!(
-            tokio::spawn(update_ip(RecordType::A, ipv4_config.clone())),
-            tokio::spawn(update_ip(RecordType::AAAA, ipv6_config.clone()))
+        let conf_json = load_conf::Config::current()?;
+        // 到达 force_refresh 间隔时本轮推送所有记录，纠正在面板上手动修改的内容
+        let mode = if mode != UpdateMode::Normal {
+            mode
+        } else if REFRESH_NEXT.swap(false, Ordering::Relaxed) {
+            UpdateMode::Refresh
+        } else if schedule::refresh_due(&conf_json) {
+            info!("{}", i18n::force_refresh_round());
+            UpdateMode::Refresh
+        } else {
+            mode
+        };
+        let (ipv4_config, ipv6_config): (Vec<_>, Vec<_>) = conf_json
+            .dns_records
+            .iter()
+            .filter(|&x| select(x))
+            .cloned()
+            .partition(|x| x.record_type == RecordType::A);
+
+        let (ipv4_result, ipv6_result) = tokio::join!(
+            tokio::spawn(update_ip(RecordType::A, ipv4_config, mode)),
+            tokio::spawn(update_ip(RecordType::AAAA, ipv6_config, mode))
         );
-        info!("本次更新完成");
+        if mode.writes_state() {
+            state::save();
+        }
+        *LAST_ROUND.lock() = Some(Utc::now());
+        info!("{}", i18n::update_round_done());
+        [ipv4_result, ipv6_result]
+            .into_iter()
+            .map(|result| result.map_err(|e| Error::Runtime(e.to_string()))?)
+            .fold(Ok(()), Result::and)
     };
 
+    if mode.writes_state() {
+        state::load();
+        // 启动时总是立即尝试所有来源与记录，不沿用上次运行留下的退避
+        backoff::reset();
+    }
+
     if conf_json.mutli_thread {
         tokio::runtime::Builder::new_multi_thread()
     } else {
@@ -58,39 +183,54 @@ pub fn run(loops_run: bool) -> Result<(), String> {
     .enable_all()
     .build()
     .map_err(|e| {
-        let e = format!("无法创建tokio runtime，回溯错误：{e}");
+        let e = Error::Runtime(i18n::runtime_create_failed(e));
         error!("{e}");
         e
     })?
     .block_on(async {
         if loops_run {
             let mut rx = LOOP_SIGNAL.1.clone();
-            #[cfg(windows)]
             let mut rx_pause = LOOP_SIGNAL.1.clone();
 
             ctrlc::set_handler(system_signal_handler).map_err(|e| {
-                let e = format!("无法创建系统信号处理器 | {e}");
+                let e = Error::Runtime(i18n::signal_handler_failed(e));
                 error!("{e}");
                 e
             })?;
+            #[cfg(unix)]
+            listen_unix_signals()?;
+            // 控制 socket 不可用时仍然正常运行，只是无法使用 ctl 命令
+            #[cfg(unix)]
+            let _control = control::listen()
+                .inspect_err(|e| warn!("{e}"))
+                .ok();
+            // 无法订阅网络变化时仍按 delay 定期运行
+            #[cfg(target_os = "linux")]
+            if load_conf::Config::current()?.watch_network {
+                let _ = netwatch::listen().inspect_err(|e| warn!("{e}"));
+            }
 
             loop {
-                run_once().await;
+                // 循环模式下错误已记录日志，继续等待下一轮
+                let _ = run_once().await;
 
+                let delay = schedule::next_round(&*load_conf::Config::current()?);
+                *NEXT_ROUND.lock() = Some(Utc::now() + delay);
                 tokio::select! {
                     _ = rx.wait_for(|signal| signal == &SignalType::Stop) => return Ok(()),
-                    _ = sleep(Duration::from_secs(conf_json.delay))=>(),
+                    _ = sleep(delay)=>(),
+                    // 重新加载、ctl trigger 或网络变化后立即开始新一轮
+                    _ = TRIGGER.notified() => (),
                 }
 
-                #[cfg(windows)]
+                *NEXT_ROUND.lock() = None;
                 tokio::select! {
                     _ = rx.wait_for(|signal| signal == &SignalType::Stop) => return Ok(()),
                     _ = rx_pause.wait_for(|signal| signal != &SignalType::Pause)=>(),
                 }
             }
         } else {
-            run_once().await;
-            return Ok(());
+            run_once().await
         }
     })
 }
@@ -101,11 +241,11 @@ fn send_service_signal(signal: SignalType) {
         .0
         .send(signal)
         .map_err(|e| {
-            let e = format!("LOOP_SIGNAL 已关闭 | {e}");
+            let e = format!("{} | {e}", i18n::loop_signal_closed());
             error!("{e}");
             e
         })
-        .expect("LOOP_SIGNAL 已关闭");
+        .unwrap_or_else(|e| panic!("{e}"));
 }
 
 #[cfg(windows)]
@@ -117,26 +257,26 @@ pub fn run_service_windows() -> Result<(), &'static str> {
         .can_pause()
         .run(|_, command| match command {
             Command::Start => {
-                task = Some(std::thread::spawn(|| match run(true) {
+                task = Some(std::thread::spawn(|| match run(true, UpdateMode::Normal, |_| true) {
                     Ok(()) => (),
                     Err(_) => {
-                        error!("检测到服务环境，强制退出进程...");
+                        error!("{}", i18n::service_force_exit());
                         std::process::exit(1)
                     }
                 }));
             }
             Command::Stop => {
-                debug!("服务退出中...");
+                debug!("{}", i18n::service_stopping());
                 send_service_signal(SignalType::Stop);
                 task.take().unwrap().join().unwrap();
             }
             Command::Pause => {
-                debug!("收到暂停信号");
-                send_service_signal(SignalType::Pause);
+                debug!("{}", i18n::service_pause_received());
+                set_paused(true);
             }
             Command::Resume => {
-                debug!("取消暂停，恢复运行...");
-                send_service_signal(SignalType::Run);
+                debug!("{}", i18n::service_resuming());
+                set_paused(false);
             }
             Command::Extended(_) => unreachable!("程序内部错误：不接受扩展命令"),
         })?;
@@ -0,0 +1,89 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use tokio::time::Duration;
+
+use crate::initialize::load_conf::Config;
+use crate::state;
+
+/// 来源连续失败的次数与下次允许请求的时间，只保存在内存中
+struct SourceBackoff {
+    failures: u32,
+    retry_at: DateTime<Utc>,
+}
+
+static SOURCES: LazyLock<Mutex<HashMap<String, SourceBackoff>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 按 jitter 百分比随机放大或缩小
+pub fn jitter(duration: Duration, config: &Config) -> Duration {
+    let percent = f64::from(config.jitter.min(100)) / 100.0;
+    let factor = 1.0 + percent * (fastrand::f64() * 2.0 - 1.0);
+    Duration::try_from_secs_f64(duration.as_secs_f64() * factor).unwrap_or(duration)
+}
+
+/// 连续失败 failures 次后的等待时间：retry_delay 每次翻倍，不超过 max_retry_delay
+fn retry_after(failures: u32, config: &Config) -> Duration {
+    let seconds = config
+        .retry_delay
+        .saturating_mul(1 << failures.saturating_sub(1).min(32))
+        .min(config.max_retry_delay);
+    jitter(Duration::from_secs(seconds), config)
+}
+
+/// 计算下次重试的时间
+pub fn retry_at(failures: u32, config: &Config) -> DateTime<Utc> {
+    after(Utc::now(), retry_after(failures, config)).unwrap_or(DateTime::<Utc>::MAX_UTC)
+}
+
+/// time 之后 duration 的时间，超出可表示的范围时返回 None
+pub fn after(time: DateTime<Utc>, duration: Duration) -> Option<DateTime<Utc>> {
+    TimeDelta::from_std(duration)
+        .ok()
+        .and_then(|delta| time.checked_add_signed(delta))
+}
+
+/// 来源仍在退避中时返回可以重试的时间
+pub fn source_waiting(source: &str) -> Option<DateTime<Utc>> {
+    SOURCES
+        .lock()
+        .get(source)
+        .map(|backoff| backoff.retry_at)
+        .filter(|retry_at| *retry_at > Utc::now())
+}
+
+pub fn source_succeeded(source: &str) {
+    SOURCES.lock().remove(source);
+}
+
+/// 记录来源失败，返回下次重试的时间
+pub fn source_failed(source: &str, config: &Config) -> DateTime<Utc> {
+    let mut sources = SOURCES.lock();
+    let backoff = sources.entry(source.to_string()).or_insert(SourceBackoff {
+        failures: 0,
+        retry_at: Utc::now(),
+    });
+    backoff.failures += 1;
+    backoff.retry_at = retry_at(backoff.failures, config);
+    backoff.retry_at
+}
+
+/// 清除所有来源与记录的退避，下一轮立即重试
+pub fn reset() {
+    SOURCES.lock().clear();
+    state::clear_retries();
+}
+
+/// 退避中的来源与记录里最早的重试时间
+pub fn earliest_retry() -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    SOURCES
+        .lock()
+        .values()
+        .map(|backoff| backoff.retry_at)
+        .chain(state::retry_times())
+        // 已经过期的时间说明对应的来源或记录本轮没有被请求，不能据此立即开始下一轮
+        .filter(|retry_at| *retry_at > now)
+        .min()
+}
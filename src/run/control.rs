@@ -0,0 +1,140 @@
+use chrono::{DateTime, Local, Utc};
+use log::{info, warn};
+use std::fs;
+use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+use std::path::PathBuf;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{UnixListener, UnixStream};
+
+use super::{LAST_ROUND, LOOP_SIGNAL, NEXT_ROUND, SignalType, TRIGGER, backoff, set_paused};
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf;
+use crate::obj::DATA_DIR;
+
+/// socket 放在只有所有者可以进入的目录中，绑定后到修改权限前也不会被其他用户连接
+pub fn socket_path() -> PathBuf {
+    DATA_DIR.join("run").join("ctl.sock")
+}
+
+/// 退出时删除 socket 文件
+pub struct Listening(PathBuf);
+
+impl Drop for Listening {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+pub fn listen() -> Result<Listening, Error> {
+    let path = socket_path();
+    let failed = |e: std::io::Error| Error::Runtime(i18n::control_listen_failed(path.display(), e));
+
+    let dir = path.parent().unwrap_or(&DATA_DIR);
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+        .map_err(failed)?;
+    fs::set_permissions(dir, fs::Permissions::from_mode(0o700)).map_err(failed)?;
+
+    if path.exists() {
+        // 能连接说明另一个实例正在使用，否则是上次异常退出遗留的文件
+        if std::os::unix::net::UnixStream::connect(&path).is_ok() {
+            return Err(Error::Runtime(i18n::control_in_use(path.display())));
+        }
+        fs::remove_file(&path).map_err(failed)?;
+    }
+    let listener = UnixListener::bind(&path).map_err(failed)?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o600)).map_err(failed)?;
+
+    tokio::spawn(async move {
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    tokio::spawn(handle(stream));
+                }
+                Err(e) => warn!("{}", i18n::control_accept_failed(e)),
+            }
+        }
+    });
+    info!("{}", i18n::control_listening(path.display()));
+    Ok(Listening(path))
+}
+
+/// 每个连接读取一行命令，回复 "ok" 或 "error" 一行，之后是说明文字
+async fn handle(stream: UnixStream) {
+    let (reader, mut writer) = stream.into_split();
+    let mut command = String::new();
+    if BufReader::new(reader).read_line(&mut command).await.is_err() {
+        return;
+    }
+    let reply = match execute(command.trim()) {
+        Ok(message) => format!("ok\n{message}\n"),
+        Err(message) => format!("error\n{message}\n"),
+    };
+    let _ = writer.write_all(reply.as_bytes()).await;
+}
+
+fn execute(command: &str) -> Result<String, String> {
+    let message = match command {
+        "pause" => {
+            if !set_paused(true) {
+                return Err(i18n::control_stopping());
+            }
+            i18n::control_paused()
+        }
+        "resume" => {
+            if !set_paused(false) {
+                return Err(i18n::control_stopping());
+            }
+            i18n::control_resumed()
+        }
+        "trigger" => {
+            if *LOOP_SIGNAL.1.borrow() == SignalType::Pause {
+                return Err(i18n::control_trigger_paused());
+            }
+            // 手动触发时不等待退避
+            backoff::reset();
+            TRIGGER.notify_one();
+            i18n::control_triggered()
+        }
+        // reload 自己会记录日志
+        "reload" => {
+            super::reload().map_err(|e| e.to_string())?;
+            return Ok(i18n::reload_done(load_conf::Config::source()));
+        }
+        "status" => return Ok(status()),
+        _ => return Err(i18n::control_unknown(command)),
+    };
+    info!("{message}");
+    Ok(message)
+}
+
+fn status() -> String {
+    let format_time = |time: Option<DateTime<Utc>>| {
+        time.map_or_else(
+            || "-".to_string(),
+            |time| {
+                time.with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string()
+            },
+        )
+    };
+    let paused = *LOOP_SIGNAL.1.borrow() == SignalType::Pause;
+    let state = if paused {
+        i18n::control_state_paused()
+    } else {
+        i18n::control_state_running()
+    };
+    // 暂停时不会按计划开始下一轮
+    let next_round = if paused { None } else { *NEXT_ROUND.lock() };
+    i18n::control_status(
+        &state,
+        std::process::id(),
+        &load_conf::Config::source(),
+        &format_time(*LAST_ROUND.lock()),
+        &format_time(next_round),
+    )
+}
@@ -0,0 +1,457 @@
+use chrono::Local;
+use log::{debug, warn};
+use std::fmt::{self, Display};
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::str::FromStr;
+use tokio::net::{UdpSocket, lookup_host};
+
+use crate::error::{Error, NetworkKind};
+use crate::i18n;
+use crate::initialize::load_conf::{Config, RecordType};
+use crate::initialize::sources::{DnsClass, DnsType, Parser, Preset, SourceConfig};
+use crate::obj::CLIENT;
+use crate::run::{backoff, filter};
+
+mod dns;
+mod stun;
+
+pub struct Detected {
+    pub ip: IpAddr,
+    pub source: String,
+}
+
+/// 预设已经按地址族展开的 IP 来源
+pub enum Source {
+    Http {
+        url: url::Url,
+        parser: Parser,
+    },
+    Dns {
+        name: String,
+        resolver: String,
+        record_type: DnsType,
+        class: DnsClass,
+    },
+    Stun {
+        server: String,
+    },
+}
+impl Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Http { url, .. } => write!(f, "{url}"),
+            // 与 dig 的写法相同
+            Source::Dns {
+                name,
+                resolver,
+                record_type,
+                class,
+            } => write!(
+                f,
+                "{name} {} {} @{resolver}",
+                class.name(),
+                record_type.name()
+            ),
+            Source::Stun { server } => write!(f, "stun:{server}"),
+        }
+    }
+}
+
+fn preset(preset: Preset, ip_version: RecordType) -> Source {
+    let v4 = ip_version == RecordType::A;
+    let http = |url: &str, parser| Source::Http {
+        url: url::Url::parse(url).unwrap(),
+        parser,
+    };
+    match preset {
+        Preset::Ipify => http(
+            if v4 {
+                "https://api.ipify.org/?format=json"
+            } else {
+                "https://api6.ipify.org/?format=json"
+            },
+            Parser::Json("/ip".to_string()),
+        ),
+        // 直接使用 IP 地址访问，保证走对应的地址族
+        Preset::Cloudflare => http(
+            if v4 {
+                "https://1.1.1.1/cdn-cgi/trace"
+            } else {
+                "https://[2606:4700:4700::1111]/cdn-cgi/trace"
+            },
+            Parser::KeyValue("ip".to_string()),
+        ),
+        Preset::Icanhazip => http(
+            if v4 {
+                "https://ipv4.icanhazip.com/"
+            } else {
+                "https://ipv6.icanhazip.com/"
+            },
+            Parser::Plain,
+        ),
+        Preset::IpSb => http(
+            if v4 {
+                "https://api-ipv4.ip.sb/ip"
+            } else {
+                "https://api-ipv6.ip.sb/ip"
+            },
+            Parser::Plain,
+        ),
+        Preset::OpenDns => Source::Dns {
+            name: "myip.opendns.com".to_string(),
+            resolver: "resolver1.opendns.com".to_string(),
+            record_type: if v4 { DnsType::A } else { DnsType::AAAA },
+            class: DnsClass::IN,
+        },
+        Preset::CloudflareDns => Source::Dns {
+            name: "whoami.cloudflare".to_string(),
+            resolver: if v4 {
+                "1.1.1.1"
+            } else {
+                "2606:4700:4700::1111"
+            }
+            .to_string(),
+            record_type: DnsType::TXT,
+            class: DnsClass::CH,
+        },
+    }
+}
+
+/// 按顺序尝试的 IP 来源，没有设置 ipv4_sources/ipv6_sources 时使用 ipv4_url/ipv6_url
+pub fn sources(ip_version: RecordType) -> Result<Vec<Source>, Error> {
+    let config = Config::current()?;
+    let (configured, url) = match ip_version {
+        RecordType::A => (&config.ipv4_sources, &config.ipv4_url),
+        RecordType::AAAA => (&config.ipv6_sources, &config.ipv6_url),
+    };
+    if configured.is_empty() {
+        return Ok(vec![Source::Http {
+            url: url.clone(),
+            parser: Parser::Regex(None),
+        }]);
+    }
+    Ok(configured
+        .iter()
+        .map(|source| match source {
+            SourceConfig::Preset(p) => preset(*p, ip_version),
+            SourceConfig::Http { url, parser } => Source::Http {
+                url: url.clone(),
+                parser: parser.clone(),
+            },
+            SourceConfig::Dns {
+                name,
+                resolver,
+                record_type,
+                class,
+            } => Source::Dns {
+                name: name.clone(),
+                resolver: resolver.clone(),
+                record_type: record_type.unwrap_or(match ip_version {
+                    RecordType::A => DnsType::A,
+                    RecordType::AAAA => DnsType::AAAA,
+                }),
+                class: *class,
+            },
+            SourceConfig::Stun { server } => Source::Stun {
+                server: server.clone(),
+            },
+        })
+        .collect())
+}
+
+/// 请求来源并返回响应正文，DNS 来源返回每行一个的回答，STUN 来源返回映射地址
+pub async fn fetch(ip_version: RecordType, source: &Source) -> Result<String, Error> {
+    match source {
+        Source::Http { url, .. } => fetch_http(ip_version, url).await,
+        Source::Dns {
+            name,
+            resolver,
+            record_type,
+            class,
+        } => dns::query(ip_version, resolver, name, *record_type, *class).await,
+        Source::Stun { server } => stun::query(ip_version, server).await,
+    }
+}
+
+async fn fetch_http(ip_version: RecordType, url: &url::Url) -> Result<String, Error> {
+    let ip_version_u8 = ip_version.as_u8();
+    let ip_response = match CLIENT.get(url.as_str()).send().await {
+        Ok(success) => success,
+        Err(error) => {
+            return Err(if error.is_timeout() {
+                Error::Network {
+                    kind: NetworkKind::Timeout,
+                    message: i18n::get_ip_timeout(ip_version_u8),
+                }
+            } else if error.is_connect() {
+                Error::Network {
+                    kind: NetworkKind::Connect,
+                    message: i18n::get_ip_connect_error(ip_version_u8, error),
+                }
+            } else if error.is_builder() {
+                Error::Config(i18n::get_ip_bad_url(ip_version_u8, error))
+            } else {
+                Error::Network {
+                    kind: NetworkKind::Other,
+                    message: i18n::get_ip_unknown_error(ip_version_u8, error),
+                }
+            });
+        }
+    };
+
+    if !ip_response.status().is_success() {
+        let status = ip_response.status().as_u16();
+        return Err(Error::Network {
+            kind: NetworkKind::Status(status),
+            message: i18n::get_ip_bad_status(ip_version_u8, status),
+        });
+    }
+
+    ip_response.text().await.map_err(|error| Error::Network {
+        kind: NetworkKind::Other,
+        message: i18n::get_ip_body_error(ip_version_u8, error),
+    })
+}
+
+/// 按来源的解析方式从响应正文中提取地址
+pub fn extract(ip_version: RecordType, source: &Source, text: &str) -> Result<IpAddr, Error> {
+    let ip_version_u8 = ip_version.as_u8();
+    let not_found = || Error::Detection(i18n::get_ip_not_found(source, ip_version_u8));
+    // 只接受对应地址族的地址
+    let parse = |text: &str| match ip_version {
+        RecordType::A => Ipv4Addr::from_str(text.trim()).ok().map(IpAddr::V4),
+        RecordType::AAAA => Ipv6Addr::from_str(text.trim()).ok().map(IpAddr::V6),
+    };
+
+    let parser = match source {
+        Source::Http { parser, .. } => parser,
+        Source::Dns { .. } | Source::Stun { .. } => {
+            return text.lines().find_map(parse).ok_or_else(not_found);
+        }
+    };
+    match parser {
+        Parser::Plain => parse(text).ok_or_else(not_found),
+        Parser::Regex(None) => {
+            let ip_text = &ip_version.re().captures(text).ok_or_else(not_found)?[0];
+            parse(ip_text).ok_or_else(|| Error::Detection(i18n::get_ip_malformed(ip_version_u8)))
+        }
+        Parser::Regex(Some(regex)) => regex
+            .captures_iter(text)
+            .filter_map(|captures| captures.name("ip"))
+            .find_map(|ip| parse(ip.as_str()))
+            .ok_or_else(not_found),
+        Parser::Json(pointer) => {
+            let value: serde_json::Value = serde_json::from_str(text)
+                .map_err(|e| Error::Detection(i18n::get_ip_json_invalid(source, e)))?;
+            match value.pointer(pointer) {
+                Some(serde_json::Value::String(ip)) => parse(ip),
+                // 返回多个地址时取第一个符合地址族的
+                Some(serde_json::Value::Array(items)) => items
+                    .iter()
+                    .filter_map(|item| item.as_str())
+                    .find_map(parse),
+                _ => None,
+            }
+            .ok_or_else(not_found)
+        }
+        Parser::KeyValue(key) => text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .find(|(k, _)| k.trim() == key)
+            .and_then(|(_, value)| parse(value))
+            .ok_or_else(not_found),
+    }
+}
+
+/// server 可以是地址（IPv6 可以带方括号）、地址加端口或主机名；
+/// DNS 与 STUN 来源回答的是连接的源地址，所以只使用与检测的地址族相同的服务器地址
+async fn resolve(
+    ip_version: RecordType,
+    server: &str,
+    default_port: u16,
+) -> Result<SocketAddr, Error> {
+    let bare = server.strip_prefix('[').and_then(|s| s.strip_suffix(']'));
+    let candidates: Vec<SocketAddr> = if let Ok(ip) = bare.unwrap_or(server).parse::<IpAddr>() {
+        vec![SocketAddr::new(ip, default_port)]
+    } else if let Ok(addr) = server.parse::<SocketAddr>() {
+        vec![addr]
+    } else {
+        let (host, port) = server
+            .rsplit_once(':')
+            .and_then(|(host, port)| Some((host, port.parse().ok()?)))
+            .unwrap_or((server, default_port));
+        lookup_host((host, port))
+            .await
+            .map_err(|e| Error::Network {
+                kind: NetworkKind::Connect,
+                message: i18n::server_lookup_failed(server, e),
+            })?
+            .collect()
+    };
+    candidates
+        .into_iter()
+        .find(|addr| addr.is_ipv4() == (ip_version == RecordType::A))
+        .ok_or_else(|| Error::Config(i18n::server_family(server, ip_version.as_u8())))
+}
+
+/// 绑定与 server 相同地址族的本地端口并连接
+async fn udp_socket(server: SocketAddr) -> io::Result<UdpSocket> {
+    let local: SocketAddr = if server.is_ipv4() {
+        (Ipv4Addr::UNSPECIFIED, 0).into()
+    } else {
+        (Ipv6Addr::UNSPECIFIED, 0).into()
+    };
+    let socket = UdpSocket::bind(local).await?;
+    socket.connect(server).await?;
+    Ok(socket)
+}
+
+/// 依次尝试所有不在退避中的来源，返回第一个成功的结果，全部失败时返回最后一个错误
+pub async fn get_ip(ip_version: RecordType) -> Result<Detected, Error> {
+    let config = Config::current()?;
+    let mut last_error = None;
+    for source in sources(ip_version)? {
+        let name = source.to_string();
+        if let Some(retry_at) = backoff::source_waiting(&name) {
+            let message = i18n::source_backing_off(
+                &source,
+                retry_at.with_timezone(&Local).format("%H:%M:%S"),
+            );
+            debug!("{message}");
+            last_error.get_or_insert(Error::Detection(message));
+            continue;
+        }
+        // 被拒绝的地址与请求失败一样处理，继续尝试下一个来源
+        match fetch(ip_version, &source)
+            .await
+            .and_then(|text| extract(ip_version, &source, &text))
+            .and_then(|ip| filter::check(ip, &config).map(|()| ip))
+        {
+            Ok(ip) => {
+                if filter::is_cgnat(ip) {
+                    warn!("{}", i18n::address_cgnat(ip));
+                }
+                backoff::source_succeeded(&name);
+                return Ok(Detected { ip, source: name });
+            }
+            Err(e) => {
+                let retry_at = backoff::source_failed(&name, &config);
+                debug!(
+                    "{source} | {e} | {}",
+                    i18n::retry_at(retry_at.with_timezone(&Local).format("%H:%M:%S"))
+                );
+                last_error = Some(e);
+            }
+        }
+    }
+    Err(last_error.unwrap())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use regex::Regex;
+
+    fn http(parser: Parser) -> Source {
+        Source::Http {
+            url: url::Url::parse("https://example.com/").unwrap(),
+            parser,
+        }
+    }
+
+    fn ok(ip_version: RecordType, parser: Parser, text: &str) -> String {
+        extract(ip_version, &http(parser), text)
+            .unwrap()
+            .to_string()
+    }
+
+    fn not_found(ip_version: RecordType, parser: Parser, text: &str) -> bool {
+        matches!(
+            extract(ip_version, &http(parser), text),
+            Err(Error::Detection(_))
+        )
+    }
+
+    #[test]
+    fn plain() {
+        assert_eq!(
+            ok(RecordType::A, Parser::Plain, " 203.0.113.7\n"),
+            "203.0.113.7"
+        );
+        assert_eq!(
+            ok(RecordType::AAAA, Parser::Plain, "2001:db8::1\n"),
+            "2001:db8::1"
+        );
+        // 只接受对应地址族的地址
+        assert!(not_found(RecordType::A, Parser::Plain, "2001:db8::1"));
+        assert!(not_found(RecordType::A, Parser::Plain, "ip=203.0.113.7"));
+    }
+
+    #[test]
+    fn regex_default() {
+        let text = "<html>Current IP Address: 203.0.113.7</html>";
+        assert_eq!(ok(RecordType::A, Parser::Regex(None), text), "203.0.113.7");
+        let text = "addr 2001:db8:0:0:1:2:3:4 end";
+        assert_eq!(
+            ok(RecordType::AAAA, Parser::Regex(None), text),
+            "2001:db8::1:2:3:4"
+        );
+        assert!(not_found(RecordType::A, Parser::Regex(None), "no address"));
+    }
+
+    #[test]
+    fn regex_custom_group() {
+        let regex = || Regex::new(r"client=(?<ip>[0-9a-f:.]+)").unwrap();
+        let text = "server=198.51.100.1 client=203.0.113.7";
+        assert_eq!(
+            ok(RecordType::A, Parser::Regex(Some(regex())), text),
+            "203.0.113.7"
+        );
+        // 第一个匹配不是对应地址族时继续尝试之后的匹配
+        let text = "client=2001:db8::1 client=203.0.113.7";
+        assert_eq!(
+            ok(RecordType::A, Parser::Regex(Some(regex())), text),
+            "203.0.113.7"
+        );
+        assert!(not_found(
+            RecordType::A,
+            Parser::Regex(Some(regex())),
+            "server=198.51.100.1"
+        ));
+    }
+
+    #[test]
+    fn json() {
+        let pointer = |pointer: &str| Parser::Json(pointer.to_string());
+        let text = r#"{"data": {"ip": "203.0.113.7"}, "list": ["2001:db8::1", "198.51.100.1"]}"#;
+        assert_eq!(ok(RecordType::A, pointer("/data/ip"), text), "203.0.113.7");
+        // 数组取第一个符合地址族的元素
+        assert_eq!(ok(RecordType::A, pointer("/list"), text), "198.51.100.1");
+        assert_eq!(ok(RecordType::AAAA, pointer("/list"), text), "2001:db8::1");
+        assert_eq!(
+            ok(RecordType::A, pointer(""), r#""203.0.113.7""#),
+            "203.0.113.7"
+        );
+        assert!(not_found(RecordType::A, pointer("/data/missing"), text));
+        assert!(not_found(RecordType::A, pointer("/data"), text));
+        assert!(not_found(
+            RecordType::A,
+            pointer("/data/ip"),
+            "203.0.113.7 not json"
+        ));
+    }
+
+    #[test]
+    fn key_value() {
+        let key = || Parser::KeyValue("ip".to_string());
+        let text = "fl=123\nh=www.cloudflare.com\nip = 203.0.113.7\nts=1700000000";
+        assert_eq!(ok(RecordType::A, key(), text), "203.0.113.7");
+        assert!(not_found(
+            RecordType::A,
+            key(),
+            "fl=123\nvisit_scheme=https"
+        ));
+        assert!(not_found(RecordType::AAAA, key(), text));
+    }
+}
@@ -0,0 +1,421 @@
+use log::debug;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::{Duration, timeout};
+
+use crate::error::{Error, NetworkKind};
+use crate::i18n;
+use crate::initialize::load_conf::RecordType;
+use crate::initialize::sources::{DnsClass, DnsType};
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+const PORT: u16 = 53;
+/// 标准查询，期望递归
+const FLAG_RD: u16 = 0x0100;
+const FLAG_QR: u16 = 0x8000;
+/// 响应被截断，需要改用 TCP
+const FLAG_TC: u16 = 0x0200;
+
+struct Response {
+    truncated: bool,
+    rcode: u16,
+    answers: Vec<String>,
+}
+
+/// 向 resolver 查询 name，返回每行一个的 A/AAAA 地址或 TXT 内容
+pub async fn query(
+    ip_version: RecordType,
+    resolver: &str,
+    name: &str,
+    record_type: DnsType,
+    class: DnsClass,
+) -> Result<String, Error> {
+    let server = super::resolve(ip_version, resolver, PORT).await?;
+    let id = fastrand::u16(..);
+    let request = build(id, name, record_type, class);
+    let malformed = || Error::Network {
+        kind: NetworkKind::Other,
+        message: i18n::dns_malformed(server),
+    };
+
+    // UDP 失败、超时或响应被截断时改用 TCP
+    let response = match timeout(TIMEOUT, udp(server, &request)).await {
+        Ok(Ok(response)) => match parse(id, &response, record_type) {
+            Some(response) if !response.truncated => Some(response),
+            Some(_) => {
+                debug!("{}", i18n::dns_truncated(server));
+                None
+            }
+            None => return Err(malformed()),
+        },
+        Ok(Err(e)) => {
+            debug!("{}", i18n::dns_udp_failed(server, e));
+            None
+        }
+        Err(_) => {
+            debug!("{}", i18n::dns_udp_timeout(server));
+            None
+        }
+    };
+    let response = match response {
+        Some(response) => response,
+        None => match timeout(TIMEOUT, tcp(server, &request)).await {
+            Ok(Ok(response)) => parse(id, &response, record_type).ok_or_else(malformed)?,
+            Ok(Err(e)) => {
+                return Err(Error::Network {
+                    kind: NetworkKind::Connect,
+                    message: i18n::dns_failed(server, e),
+                });
+            }
+            Err(_) => {
+                return Err(Error::Network {
+                    kind: NetworkKind::Timeout,
+                    message: i18n::dns_timeout(server),
+                });
+            }
+        },
+    };
+
+    if response.rcode != 0 {
+        let rcode = match response.rcode {
+            1 => "FORMERR",
+            2 => "SERVFAIL",
+            3 => "NXDOMAIN",
+            4 => "NOTIMP",
+            5 => "REFUSED",
+            _ => "",
+        };
+        return Err(Error::Network {
+            kind: NetworkKind::Other,
+            message: i18n::dns_rcode(server, response.rcode, rcode),
+        });
+    }
+    Ok(response.answers.join("\n"))
+}
+
+fn build(id: u16, name: &str, record_type: DnsType, class: DnsClass) -> Vec<u8> {
+    let mut request = Vec::with_capacity(name.len() + 18);
+    request.extend(id.to_be_bytes());
+    request.extend(FLAG_RD.to_be_bytes());
+    // QDCOUNT=1，其余为 0
+    request.extend([0, 1, 0, 0, 0, 0, 0, 0]);
+    for label in name.trim_end_matches('.').split('.') {
+        request.push(label.len() as u8);
+        request.extend(label.as_bytes());
+    }
+    request.push(0);
+    request.extend(record_type.code().to_be_bytes());
+    request.extend(class.code().to_be_bytes());
+    request
+}
+
+async fn udp(server: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let socket = super::udp_socket(server).await?;
+    socket.send(request).await?;
+    let mut buf = vec![0; 4096];
+    loop {
+        let len = socket.recv(&mut buf).await?;
+        // 忽略 ID 不符的报文
+        if len >= 2 && buf[..2] == request[..2] {
+            buf.truncate(len);
+            return Ok(buf);
+        }
+    }
+}
+
+/// TCP 上的报文前有两字节长度
+async fn tcp(server: SocketAddr, request: &[u8]) -> io::Result<Vec<u8>> {
+    let mut stream = TcpStream::connect(server).await?;
+    let mut message = (request.len() as u16).to_be_bytes().to_vec();
+    message.extend(request);
+    stream.write_all(&message).await?;
+    let len = stream.read_u16().await?;
+    let mut buf = vec![0; usize::from(len)];
+    stream.read_exact(&mut buf).await?;
+    Ok(buf)
+}
+
+fn read_u16(buf: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(pos)?, *buf.get(pos + 1)?]))
+}
+
+/// 跳过可能被压缩的域名，返回之后的位置
+fn skip_name(buf: &[u8], mut pos: usize) -> Option<usize> {
+    loop {
+        let len = *buf.get(pos)?;
+        match len {
+            0 => return Some(pos + 1),
+            len if len & 0xC0 == 0xC0 => return Some(pos + 2),
+            len => pos += 1 + usize::from(len),
+        }
+    }
+}
+
+/// 只取与查询类型相同的回答，忽略 CNAME 等；格式不正确时返回 None
+fn parse(id: u16, buf: &[u8], record_type: DnsType) -> Option<Response> {
+    let flags = read_u16(buf, 2)?;
+    if read_u16(buf, 0)? != id || flags & FLAG_QR == 0 {
+        return None;
+    }
+    let qdcount = read_u16(buf, 4)?;
+    let ancount = read_u16(buf, 6)?;
+    let mut pos = 12;
+    for _ in 0..qdcount {
+        pos = skip_name(buf, pos)? + 4;
+    }
+
+    let mut answers = Vec::new();
+    for _ in 0..ancount {
+        pos = skip_name(buf, pos)?;
+        let answer_type = read_u16(buf, pos)?;
+        let rdlength = usize::from(read_u16(buf, pos + 8)?);
+        pos += 10;
+        let rdata = buf.get(pos..pos + rdlength)?;
+        pos += rdlength;
+        if answer_type != record_type.code() {
+            continue;
+        }
+        match record_type {
+            DnsType::A => {
+                let octets: [u8; 4] = rdata.try_into().ok()?;
+                answers.push(Ipv4Addr::from(octets).to_string());
+            }
+            DnsType::AAAA => {
+                let octets: [u8; 16] = rdata.try_into().ok()?;
+                answers.push(Ipv6Addr::from(octets).to_string());
+            }
+            // 由若干个带长度前缀的字符串组成
+            DnsType::TXT => {
+                let mut text = Vec::new();
+                let mut rest = rdata;
+                while let Some((&len, tail)) = rest.split_first() {
+                    text.extend(tail.get(..usize::from(len))?);
+                    rest = &tail[usize::from(len)..];
+                }
+                answers.push(String::from_utf8_lossy(&text).into_owned());
+            }
+        }
+    }
+    Some(Response {
+        truncated: flags & FLAG_TC != 0,
+        rcode: flags & 0x000F,
+        answers,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::{TcpListener, UdpSocket};
+
+    const ID: u16 = 0x1234;
+    const CNAME: u16 = 5;
+
+    /// 回答 request 的响应，回答的名称都压缩为指向问题中名称的指针
+    fn response(request: &[u8], flags: u16, answers: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut buf = request[..2].to_vec();
+        buf.extend((FLAG_QR | flags).to_be_bytes());
+        buf.extend([0, 1]);
+        buf.extend((answers.len() as u16).to_be_bytes());
+        buf.extend([0, 0, 0, 0]);
+        buf.extend(&request[12..]);
+        for (answer_type, rdata) in answers {
+            buf.extend([0xC0, 12]);
+            buf.extend(answer_type.to_be_bytes());
+            // 类别与问题相同
+            buf.extend(&request[request.len() - 2..]);
+            buf.extend(300u32.to_be_bytes());
+            buf.extend((rdata.len() as u16).to_be_bytes());
+            buf.extend(*rdata);
+        }
+        buf
+    }
+
+    fn request(record_type: DnsType, class: DnsClass) -> Vec<u8> {
+        build(ID, "myip.opendns.com", record_type, class)
+    }
+
+    #[test]
+    fn build_question() {
+        let request = build(ID, "whoami.cloudflare.", DnsType::TXT, DnsClass::CH);
+        let mut expected = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+        expected.extend(b"\x06whoami\x0acloudflare\x00");
+        expected.extend([0, 16, 0, 3]);
+        assert_eq!(request, expected);
+
+        let request = build(ID, "myip.opendns.com", DnsType::AAAA, DnsClass::IN);
+        assert_eq!(request[request.len() - 4..], [0, 28, 0, 1]);
+    }
+
+    #[test]
+    fn skip_names() {
+        let buf = b"\x04myip\x07opendns\x03com\x00\xC0\x00\x03www\xC0\x00";
+        assert_eq!(skip_name(buf, 0), Some(18));
+        assert_eq!(skip_name(buf, 18), Some(20));
+        assert_eq!(skip_name(buf, 20), Some(26));
+        // 标签超出报文
+        assert_eq!(skip_name(b"\x04my", 0), None);
+        assert_eq!(skip_name(b"", 0), None);
+    }
+
+    #[test]
+    fn compressed_answers() {
+        let request = request(DnsType::A, DnsClass::IN);
+        let buf = response(
+            &request,
+            0,
+            &[
+                (CNAME, b"\x03www\xC0\x0c"),
+                (DnsType::A.code(), &[203, 0, 113, 7]),
+                (DnsType::A.code(), &[198, 51, 100, 1]),
+            ],
+        );
+        let response = parse(ID, &buf, DnsType::A).unwrap();
+        assert!(!response.truncated);
+        assert_eq!(response.rcode, 0);
+        assert_eq!(response.answers, ["203.0.113.7", "198.51.100.1"]);
+    }
+
+    #[test]
+    fn pointer_loop() {
+        let request = request(DnsType::A, DnsClass::IN);
+        let mut buf = response(&request, 0, &[(DnsType::A.code(), &[203, 0, 113, 7])]);
+        // 回答的名称指向自己，只跳过指针而不跟随，所以不会死循环
+        let answer = request.len();
+        buf[answer + 1] = answer as u8;
+        assert_eq!(skip_name(&buf, answer), Some(answer + 2));
+        let response = parse(ID, &buf, DnsType::A).unwrap();
+        assert_eq!(response.answers, ["203.0.113.7"]);
+    }
+
+    #[test]
+    fn multi_string_txt() {
+        let request = build(ID, "whoami.cloudflare", DnsType::TXT, DnsClass::CH);
+        let buf = response(
+            &request,
+            0,
+            &[(DnsType::TXT.code(), b"\x062001:d\x0bb8::1234:56\x00\x0278")],
+        );
+        // 回答的类别为 CH
+        let answer = request.len();
+        assert_eq!(buf[answer + 4..answer + 6], [0, 3]);
+        let parsed = parse(ID, &buf, DnsType::TXT).unwrap();
+        assert_eq!(parsed.answers, ["2001:db8::1234:5678"]);
+
+        // 字符串长度超出 rdata
+        let buf = response(&request, 0, &[(DnsType::TXT.code(), b"\x09abc")]);
+        assert!(parse(ID, &buf, DnsType::TXT).is_none());
+    }
+
+    #[test]
+    fn rcode_and_truncated_flag() {
+        let request = request(DnsType::A, DnsClass::IN);
+        let nxdomain = response(&request, 3, &[]);
+        let parsed = parse(ID, &nxdomain, DnsType::A).unwrap();
+        assert_eq!(parsed.rcode, 3);
+        assert!(parsed.answers.is_empty());
+
+        let truncated = response(&request, FLAG_TC, &[]);
+        assert!(parse(ID, &truncated, DnsType::A).unwrap().truncated);
+    }
+
+    #[test]
+    fn mismatched_id_or_query() {
+        let request = request(DnsType::A, DnsClass::IN);
+        let buf = response(&request, 0, &[(DnsType::A.code(), &[203, 0, 113, 7])]);
+        assert!(parse(ID + 1, &buf, DnsType::A).is_none());
+        // 没有 QR 标志的是查询而不是响应
+        assert!(parse(ID, &request, DnsType::A).is_none());
+    }
+
+    #[test]
+    fn truncated_answers() {
+        let request = request(DnsType::A, DnsClass::IN);
+        let buf = response(&request, 0, &[(DnsType::A.code(), &[203, 0, 113, 7])]);
+        for len in [4, 11, request.len() - 1, request.len() + 11, buf.len() - 1] {
+            assert!(parse(ID, &buf[..len], DnsType::A).is_none(), "len {len}");
+        }
+        // A 记录的地址长度不正确
+        let buf = response(&request, 0, &[(DnsType::A.code(), &[203, 0, 113])]);
+        assert!(parse(ID, &buf, DnsType::A).is_none());
+    }
+
+    /// 同一端口上的 UDP 与 TCP 监听
+    async fn bind() -> (UdpSocket, TcpListener) {
+        loop {
+            let udp = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            if let Ok(tcp) = TcpListener::bind(udp.local_addr().unwrap()).await {
+                return (udp, tcp);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn truncated_udp_falls_back_to_tcp() {
+        let (udp, tcp) = bind().await;
+        let server = udp.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            let (len, peer) = udp.recv_from(&mut buf).await.unwrap();
+            let truncated = response(&buf[..len], FLAG_TC, &[]);
+            udp.send_to(&truncated, peer).await.unwrap();
+
+            let (mut stream, _) = tcp.accept().await.unwrap();
+            let len = stream.read_u16().await.unwrap();
+            let mut request = vec![0; usize::from(len)];
+            stream.read_exact(&mut request).await.unwrap();
+            let full = response(
+                &request,
+                0,
+                &[
+                    (DnsType::TXT.code(), b"\x0b203.0.113.7"),
+                    (DnsType::TXT.code(), b"\x03foo\x03bar"),
+                ],
+            );
+            let mut message = (full.len() as u16).to_be_bytes().to_vec();
+            message.extend(full);
+            stream.write_all(&message).await.unwrap();
+        });
+
+        let answers = query(
+            RecordType::A,
+            &server,
+            "whoami.cloudflare",
+            DnsType::TXT,
+            DnsClass::CH,
+        )
+        .await
+        .unwrap();
+        assert_eq!(answers, "203.0.113.7\nfoobar");
+    }
+
+    #[tokio::test]
+    async fn rcode_is_an_error() {
+        let (udp, _tcp) = bind().await;
+        let server = udp.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 512];
+            let (len, peer) = udp.recv_from(&mut buf).await.unwrap();
+            let refused = response(&buf[..len], 5, &[]);
+            udp.send_to(&refused, peer).await.unwrap();
+        });
+
+        let result = query(
+            RecordType::A,
+            &server,
+            "myip.opendns.com",
+            DnsType::A,
+            DnsClass::IN,
+        )
+        .await;
+        assert!(matches!(
+            result,
+            Err(Error::Network {
+                kind: NetworkKind::Other,
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,310 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use tokio::time::{Duration, Instant, timeout_at};
+
+use crate::error::{Error, NetworkKind};
+use crate::i18n;
+use crate::initialize::load_conf::RecordType;
+
+const PORT: u16 = 3478;
+const MAGIC_COOKIE: [u8; 4] = 0x2112_A442_u32.to_be_bytes();
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const BINDING_ERROR: u16 = 0x0111;
+const MAPPED_ADDRESS: u16 = 0x0001;
+const ERROR_CODE: u16 = 0x0009;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+/// UDP 可能丢包，按 RFC 5389 从 500ms 开始翻倍重传
+const RETRANSMIT: [Duration; 4] = [
+    Duration::from_millis(500),
+    Duration::from_secs(1),
+    Duration::from_secs(2),
+    Duration::from_secs(2),
+];
+
+/// 发送 Binding 请求，返回服务器看到的映射地址
+pub async fn query(ip_version: RecordType, server: &str) -> Result<String, Error> {
+    let addr = super::resolve(ip_version, server, PORT).await?;
+    let failed = |e| Error::Network {
+        kind: NetworkKind::Connect,
+        message: i18n::stun_failed(addr, e),
+    };
+    let transaction: [u8; 12] = std::array::from_fn(|_| fastrand::u8(..));
+    let mut request = Vec::with_capacity(20);
+    request.extend(BINDING_REQUEST.to_be_bytes());
+    request.extend(0u16.to_be_bytes());
+    request.extend(MAGIC_COOKIE);
+    request.extend(transaction);
+
+    let socket = super::udp_socket(addr).await.map_err(failed)?;
+    let mut buf = vec![0; 1500];
+    for wait in RETRANSMIT {
+        socket.send(&request).await.map_err(failed)?;
+        let deadline = Instant::now() + wait;
+        while let Ok(received) = timeout_at(deadline, socket.recv(&mut buf)).await {
+            let len = received.map_err(failed)?;
+            // 不属于本次请求的报文直接忽略
+            if let Some(result) = parse(&transaction, &buf[..len]) {
+                return result
+                    .map(|ip| ip.to_string())
+                    .map_err(|message| Error::Network {
+                        kind: NetworkKind::Other,
+                        message: i18n::stun_error(addr, message),
+                    });
+            }
+        }
+    }
+    Err(Error::Network {
+        kind: NetworkKind::Timeout,
+        message: i18n::stun_timeout(addr),
+    })
+}
+
+/// 事务 ID 不符时返回 None
+fn parse(transaction: &[u8; 12], buf: &[u8]) -> Option<Result<IpAddr, String>> {
+    if buf.get(4..8)? != MAGIC_COOKIE || buf.get(8..20)? != transaction {
+        return None;
+    }
+    let message_type = u16::from_be_bytes([buf[0], buf[1]]);
+    let length = usize::from(u16::from_be_bytes([buf[2], buf[3]]));
+    let Some(mut attributes) = buf.get(20..20 + length) else {
+        return Some(Err(i18n::stun_malformed()));
+    };
+
+    let mut mapped = None;
+    let mut xor_mapped = None;
+    let mut error = None;
+    while attributes.len() >= 4 {
+        let attribute_type = u16::from_be_bytes([attributes[0], attributes[1]]);
+        let attribute_length = usize::from(u16::from_be_bytes([attributes[2], attributes[3]]));
+        let Some(value) = attributes.get(4..4 + attribute_length) else {
+            break;
+        };
+        match attribute_type {
+            MAPPED_ADDRESS => mapped = address(value, None),
+            XOR_MAPPED_ADDRESS => xor_mapped = address(value, Some(transaction)),
+            // 2 字节保留，之后是错误类别、编号与原因
+            ERROR_CODE if value.len() >= 4 => {
+                let code = u16::from(value[2] & 0x07) * 100 + u16::from(value[3]);
+                error = Some(format!("{code} {}", String::from_utf8_lossy(&value[4..])));
+            }
+            _ => (),
+        }
+        // 属性按 4 字节对齐
+        attributes = attributes
+            .get((4 + attribute_length + 3) & !3..)
+            .unwrap_or_default();
+    }
+
+    Some(match message_type {
+        // 优先使用 XOR-MAPPED-ADDRESS，旧服务器只返回 MAPPED-ADDRESS
+        BINDING_SUCCESS => xor_mapped.or(mapped).ok_or_else(i18n::stun_malformed),
+        BINDING_ERROR => Err(error.unwrap_or_else(i18n::stun_malformed)),
+        _ => Err(i18n::stun_malformed()),
+    })
+}
+
+/// 1 字节保留、1 字节地址族、2 字节端口，之后是地址；XOR 时与 magic cookie 加事务 ID 异或
+fn address(value: &[u8], transaction: Option<&[u8; 12]>) -> Option<IpAddr> {
+    let mut key = [0; 16];
+    if let Some(transaction) = transaction {
+        key[..4].copy_from_slice(&MAGIC_COOKIE);
+        key[4..].copy_from_slice(transaction);
+    }
+    let xor = |bytes: &[u8]| -> Vec<u8> { bytes.iter().zip(key).map(|(b, k)| b ^ k).collect() };
+    match value.get(1)? {
+        0x01 => {
+            let octets: [u8; 4] = xor(value.get(4..8)?).try_into().ok()?;
+            Some(IpAddr::V4(Ipv4Addr::from(octets)))
+        }
+        0x02 => {
+            let octets: [u8; 16] = xor(value.get(4..20)?).try_into().ok()?;
+            Some(IpAddr::V6(Ipv6Addr::from(octets)))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::UdpSocket;
+
+    const TRANSACTION: [u8; 12] = *b"0123456789ab";
+
+    /// 地址属性的值，transaction 为 Some 时按 XOR-MAPPED-ADDRESS 编码
+    fn address_value(ip: IpAddr, transaction: Option<&[u8; 12]>) -> Vec<u8> {
+        let mut key = [0; 16];
+        if let Some(transaction) = transaction {
+            key[..4].copy_from_slice(&MAGIC_COOKIE);
+            key[4..].copy_from_slice(transaction);
+        }
+        let (family, octets) = match ip {
+            IpAddr::V4(ip) => (0x01, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (0x02, ip.octets().to_vec()),
+        };
+        let mut value = vec![0, family, 0x12, 0x34];
+        value.extend(octets.iter().zip(key).map(|(b, k)| b ^ k));
+        value
+    }
+
+    fn message(
+        message_type: u16,
+        transaction: &[u8; 12],
+        attributes: &[(u16, Vec<u8>)],
+    ) -> Vec<u8> {
+        let mut body = Vec::new();
+        for (attribute_type, value) in attributes {
+            body.extend(attribute_type.to_be_bytes());
+            body.extend((value.len() as u16).to_be_bytes());
+            body.extend(value);
+            body.resize((body.len() + 3) & !3, 0);
+        }
+        let mut buf = Vec::new();
+        buf.extend(message_type.to_be_bytes());
+        buf.extend((body.len() as u16).to_be_bytes());
+        buf.extend(MAGIC_COOKIE);
+        buf.extend(transaction);
+        buf.extend(body);
+        buf
+    }
+
+    #[test]
+    fn xor_mapped_address_preferred() {
+        let xor = IpAddr::from([203, 0, 113, 7]);
+        let plain = IpAddr::from([198, 51, 100, 1]);
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[
+                (MAPPED_ADDRESS, address_value(plain, None)),
+                (XOR_MAPPED_ADDRESS, address_value(xor, Some(&TRANSACTION))),
+            ],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), Some(Ok(xor)));
+    }
+
+    #[test]
+    fn mapped_address_fallback() {
+        let ip = IpAddr::from([198, 51, 100, 1]);
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[(MAPPED_ADDRESS, address_value(ip, None))],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), Some(Ok(ip)));
+    }
+
+    #[test]
+    fn ipv6_addresses() {
+        let ip: IpAddr = "2001:db8::1234:5678".parse().unwrap();
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[(XOR_MAPPED_ADDRESS, address_value(ip, Some(&TRANSACTION)))],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), Some(Ok(ip)));
+
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[(MAPPED_ADDRESS, address_value(ip, None))],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), Some(Ok(ip)));
+    }
+
+    #[test]
+    fn error_response() {
+        let mut value = vec![0, 0, 0x04, 0x01];
+        value.extend(b"Unauthorized");
+        let buf = message(BINDING_ERROR, &TRANSACTION, &[(ERROR_CODE, value)]);
+        assert_eq!(
+            parse(&TRANSACTION, &buf),
+            Some(Err("401 Unauthorized".to_string()))
+        );
+    }
+
+    #[test]
+    fn other_transactions_ignored() {
+        let ip = IpAddr::from([203, 0, 113, 7]);
+        let other = *b"ba9876543210";
+        let buf = message(
+            BINDING_SUCCESS,
+            &other,
+            &[(XOR_MAPPED_ADDRESS, address_value(ip, Some(&other)))],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), None);
+
+        let mut buf = message(BINDING_SUCCESS, &TRANSACTION, &[]);
+        buf[4] = 0;
+        assert_eq!(parse(&TRANSACTION, &buf), None);
+        assert_eq!(parse(&TRANSACTION, &buf[..12]), None);
+    }
+
+    #[test]
+    fn truncated_messages() {
+        let ip = IpAddr::from([203, 0, 113, 7]);
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[(XOR_MAPPED_ADDRESS, address_value(ip, Some(&TRANSACTION)))],
+        );
+        let malformed = Some(Err(i18n::stun_malformed()));
+
+        // 消息长度超出收到的数据
+        assert_eq!(parse(&TRANSACTION, &buf[..buf.len() - 1]), malformed);
+
+        // 属性长度超出消息长度
+        let mut short = buf.clone();
+        short[23] = 0xff;
+        assert_eq!(parse(&TRANSACTION, &short), malformed);
+
+        // 地址比地址族要求的短
+        let buf = message(
+            BINDING_SUCCESS,
+            &TRANSACTION,
+            &[(
+                XOR_MAPPED_ADDRESS,
+                address_value(ip, Some(&TRANSACTION))[..6].to_vec(),
+            )],
+        );
+        assert_eq!(parse(&TRANSACTION, &buf), malformed);
+    }
+
+    #[tokio::test]
+    async fn query_local_responder() {
+        let responder = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let server = responder.local_addr().unwrap().to_string();
+        tokio::spawn(async move {
+            let mut buf = [0; 1500];
+            let (len, peer) = responder.recv_from(&mut buf).await.unwrap();
+            assert_eq!(len, 20);
+            assert_eq!(u16::from_be_bytes([buf[0], buf[1]]), BINDING_REQUEST);
+            let transaction: [u8; 12] = buf[8..20].try_into().unwrap();
+
+            // 先发送一个不属于本次请求的响应，应当被忽略
+            let other = *b"ba9876543210";
+            let stray = message(
+                BINDING_SUCCESS,
+                &other,
+                &[(
+                    XOR_MAPPED_ADDRESS,
+                    address_value([192, 0, 2, 1].into(), Some(&other)),
+                )],
+            );
+            responder.send_to(&stray, peer).await.unwrap();
+
+            let response = message(
+                BINDING_SUCCESS,
+                &transaction,
+                &[(
+                    XOR_MAPPED_ADDRESS,
+                    address_value(peer.ip(), Some(&transaction)),
+                )],
+            );
+            responder.send_to(&response, peer).await.unwrap();
+        });
+
+        let ip = query(RecordType::A, &server).await.unwrap();
+        assert_eq!(ip, "127.0.0.1");
+    }
+}
@@ -0,0 +1,93 @@
+use ipnet::{IpNet, Ipv4Net, Ipv6Net};
+use std::net::IpAddr;
+use std::sync::LazyLock;
+
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::Config;
+
+/// 不会出现在公网上的 IPv4 范围（RFC 6890 等）
+static IPV4_BOGONS: LazyLock<Vec<Ipv4Net>> = LazyLock::new(|| {
+    [
+        "0.0.0.0/8",
+        "10.0.0.0/8",
+        "100.64.0.0/10",
+        "127.0.0.0/8",
+        "169.254.0.0/16",
+        "172.16.0.0/12",
+        "192.0.0.0/24",
+        "192.0.2.0/24",
+        "192.168.0.0/16",
+        "198.18.0.0/15",
+        "198.51.100.0/24",
+        "203.0.113.0/24",
+        "224.0.0.0/4",
+        "240.0.0.0/4",
+    ]
+    .iter()
+    .map(|net| net.parse().unwrap())
+    .collect()
+});
+
+/// 全局单播只分配在 2000::/3 中，再排除其中的文档地址
+static IPV6_GLOBAL: LazyLock<Ipv6Net> = LazyLock::new(|| "2000::/3".parse().unwrap());
+static IPV6_BOGONS: LazyLock<Vec<Ipv6Net>> = LazyLock::new(|| {
+    ["2001:db8::/32", "3fff::/20"]
+        .iter()
+        .map(|net| net.parse().unwrap())
+        .collect()
+});
+
+static CGNAT: LazyLock<Ipv4Net> = LazyLock::new(|| "100.64.0.0/10".parse().unwrap());
+
+/// 运营商级 NAT 的共享地址，记录指向它时外部无法访问
+pub fn is_cgnat(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ipv4) => CGNAT.contains(&ipv4),
+        IpAddr::V6(_) => false,
+    }
+}
+
+/// 命中的内置保留范围，IPv6 只检查 2000::/3 之内的部分
+fn bogon(ip: IpAddr) -> Option<IpNet> {
+    match ip {
+        IpAddr::V4(ipv4) => IPV4_BOGONS
+            .iter()
+            .find(|net| net.contains(&ipv4))
+            .map(|net| IpNet::V4(*net)),
+        IpAddr::V6(ipv6) => IPV6_BOGONS
+            .iter()
+            .find(|net| net.contains(&ipv6))
+            .map(|net| IpNet::V6(*net)),
+    }
+}
+
+/// 按 deny、allow、内置保留范围的顺序检查获取到的地址，拒绝时返回原因
+pub fn check(ip: IpAddr, config: &Config) -> Result<(), Error> {
+    let (deny, allow): (Vec<IpNet>, Vec<IpNet>) = match ip {
+        IpAddr::V4(_) => (
+            config.ipv4_deny.iter().copied().map(IpNet::V4).collect(),
+            config.ipv4_allow.iter().copied().map(IpNet::V4).collect(),
+        ),
+        IpAddr::V6(_) => (
+            config.ipv6_deny.iter().copied().map(IpNet::V6).collect(),
+            config.ipv6_allow.iter().copied().map(IpNet::V6).collect(),
+        ),
+    };
+    if let Some(net) = deny.iter().find(|net| net.contains(&ip)) {
+        return Err(Error::Detection(i18n::address_denied(ip, net)));
+    }
+    if allow.iter().any(|net| net.contains(&ip)) {
+        return Ok(());
+    }
+    if let IpAddr::V6(ipv6) = ip
+        && !IPV6_GLOBAL.contains(&ipv6)
+    {
+        return Err(Error::Detection(i18n::address_not_global(ip)));
+    }
+    match bogon(ip) {
+        Some(_) if is_cgnat(ip) => Err(Error::Detection(i18n::address_cgnat_rejected(ip))),
+        Some(net) => Err(Error::Detection(i18n::address_bogon(ip, net))),
+        None => Ok(()),
+    }
+}
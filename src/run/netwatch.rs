@@ -0,0 +1,118 @@
+use chrono::Utc;
+use log::{debug, info, warn};
+use netlink_sys::{Socket, SocketAddr, protocols::NETLINK_ROUTE};
+use std::io;
+use tokio::io::unix::AsyncFd;
+use tokio::time::{Duration, timeout};
+
+use super::{TRIGGER, backoff, schedule};
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf;
+
+// linux/rtnetlink.h
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+const RTM_NEWADDR: u16 = 20;
+const RTM_DELADDR: u16 = 21;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_DELROUTE: u16 = 25;
+const RT_SCOPE_UNIVERSE: u8 = 0;
+const RT_TABLE_MAIN: u8 = 254;
+const NLMSG_HDRLEN: usize = 16;
+/// 接收缓冲区溢出，说明期间有消息丢失
+const ENOBUFS: i32 = 105;
+
+/// 订阅地址与路由变化，变化稳定后唤醒循环
+pub fn listen() -> Result<(), Error> {
+    let failed = |e| Error::Runtime(i18n::netwatch_failed(e));
+    let mut socket = Socket::new(NETLINK_ROUTE).map_err(failed)?;
+    socket
+        .bind(&SocketAddr::new(
+            0,
+            RTMGRP_IPV4_IFADDR | RTMGRP_IPV4_ROUTE | RTMGRP_IPV6_IFADDR | RTMGRP_IPV6_ROUTE,
+        ))
+        .map_err(failed)?;
+    socket.set_non_blocking(true).map_err(failed)?;
+    let socket = AsyncFd::new(socket).map_err(failed)?;
+
+    tokio::spawn(async move {
+        let mut buf = Vec::with_capacity(8192);
+        loop {
+            if let Err(e) = changed(&socket, &mut buf).await {
+                warn!("{}", i18n::netwatch_failed(e));
+                return;
+            }
+            // 拨号、切换线路时通常会连续产生多条消息，直到安静 network_debounce 秒后才触发
+            loop {
+                let debounce = load_conf::Config::current()
+                    .map_or(load_conf::get_default_network_debounce(), |conf| {
+                        conf.network_debounce
+                    });
+                match timeout(Duration::from_secs(debounce), changed(&socket, &mut buf)).await {
+                    Ok(Ok(())) => continue,
+                    Ok(Err(e)) => {
+                        warn!("{}", i18n::netwatch_failed(e));
+                        return;
+                    }
+                    Err(_) => break,
+                }
+            }
+            // 静默时段内不提前更新，时段结束后的一轮会获取到新的地址
+            if load_conf::Config::current()
+                .is_ok_and(|conf| schedule::quiet_until(&conf, Utc::now()).is_some())
+            {
+                debug!("{}", i18n::network_changed_quiet());
+                continue;
+            }
+            // 网络恢复后之前的失败多半已经不再适用
+            info!("{}", i18n::network_changed());
+            backoff::reset();
+            TRIGGER.notify_one();
+        }
+    });
+    debug!("{}", i18n::netwatch_listening());
+    Ok(())
+}
+
+/// 等待下一条可能影响公网地址的消息
+async fn changed(socket: &AsyncFd<Socket>, buf: &mut Vec<u8>) -> io::Result<()> {
+    loop {
+        let mut guard = socket.readable().await?;
+        buf.clear();
+        match guard.try_io(|inner| inner.get_ref().recv(buf, 0)) {
+            Ok(Ok(_)) if is_relevant(buf) => return Ok(()),
+            Ok(Ok(_)) => (),
+            Ok(Err(e)) if e.raw_os_error() == Some(ENOBUFS) => return Ok(()),
+            Ok(Err(e)) => return Err(e),
+            Err(_would_block) => (),
+        }
+    }
+}
+
+/// 只关心全局地址与主路由表中默认路由的变化，忽略链路本地地址等
+fn is_relevant(mut buf: &[u8]) -> bool {
+    while buf.len() >= NLMSG_HDRLEN {
+        let len = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        if len < NLMSG_HDRLEN || len > buf.len() {
+            break;
+        }
+        let payload = &buf[NLMSG_HDRLEN..len];
+        let relevant = match u16::from_ne_bytes([buf[4], buf[5]]) {
+            // ifaddrmsg: family, prefixlen, flags, scope
+            RTM_NEWADDR | RTM_DELADDR => payload.get(3) == Some(&RT_SCOPE_UNIVERSE),
+            // rtmsg: family, dst_len, src_len, tos, table
+            RTM_NEWROUTE | RTM_DELROUTE => {
+                payload.get(1) == Some(&0) && payload.get(4) == Some(&RT_TABLE_MAIN)
+            }
+            _ => false,
+        };
+        if relevant {
+            return true;
+        }
+        buf = buf.get((len + 3) & !3..).unwrap_or_default();
+    }
+    false
+}
@@ -0,0 +1,108 @@
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use parking_lot::Mutex;
+use tokio::time::Duration;
+
+use crate::initialize::load_conf::{Config, StableFor};
+use crate::run::backoff;
+use crate::state;
+
+/// 上次推送所有记录的时间，用于 force_refresh
+static LAST_REFRESH: Mutex<Option<DateTime<Utc>>> = Mutex::new(None);
+
+fn refresh_interval(config: &Config) -> Option<TimeDelta> {
+    i64::try_from(config.force_refresh)
+        .ok()
+        .filter(|seconds| *seconds > 0)
+        .and_then(TimeDelta::try_seconds)
+}
+
+/// 本轮是否需要无论 IP 是否变化都推送所有记录
+pub fn refresh_due(config: &Config) -> bool {
+    let Some(interval) = refresh_interval(config) else {
+        return false;
+    };
+    let now = Utc::now();
+    let mut last = LAST_REFRESH.lock();
+    match *last {
+        // 启动后的第一轮 IP 缓存为空，本来就会推送所有记录
+        None => {
+            *last = Some(now);
+            false
+        }
+        Some(time) if now - time >= interval => {
+            *last = Some(now);
+            true
+        }
+        Some(_) => false,
+    }
+}
+
+/// 当前处于静默时段时返回时段结束的时间，相邻或重叠的时段会连续推迟
+pub fn quiet_until(config: &Config, time: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut until = None;
+    let mut local = time.with_timezone(&Local);
+    // 每个时段最多推迟一次，避免全天都是静默时段时死循环
+    for _ in 0..config.quiet_hours.len() {
+        let Some(window) = config
+            .quiet_hours
+            .iter()
+            .find(|window| window.contains(local.time()))
+        else {
+            break;
+        };
+        let mut end = local.date_naive().and_time(window.end);
+        if end <= local.naive_local() {
+            end += TimeDelta::days(1);
+        }
+        // 夏令时切换导致本地时间不存在时，按一小时后计算
+        local = end
+            .and_local_timezone(Local)
+            .earliest()
+            .unwrap_or(local + TimeDelta::hours(1));
+        until = Some(local.with_timezone(&Utc));
+    }
+    until
+}
+
+/// 下一轮前等待的时间
+///
+/// 按 schedule（未设置时为带随机浮动的 delay）计划下一轮，有来源或记录在退避中、
+/// 到达 force_refresh 间隔或等待中的地址到期时提前，落在静默时段内时推迟到时段结束
+pub fn next_round(config: &Config) -> Duration {
+    let now = Utc::now();
+    let planned = config
+        .schedule
+        .as_ref()
+        .and_then(|cron| cron.find_next_occurrence(&Local::now(), false).ok())
+        .map(|time| time.with_timezone(&Utc))
+        .or_else(|| {
+            let delay = backoff::jitter(Duration::from_secs(config.delay), config);
+            backoff::after(now, delay)
+        })
+        .unwrap_or(DateTime::<Utc>::MAX_UTC);
+    let refresh = refresh_interval(config)
+        .zip(*LAST_REFRESH.lock())
+        .and_then(|(interval, last)| last.checked_add_signed(interval));
+
+    // 按时长等待稳定的地址在到期时立即检查
+    let stable = match config.stable_for {
+        StableFor::Seconds(seconds) => {
+            let duration = i64::try_from(seconds).ok().and_then(TimeDelta::try_seconds);
+            state::pending_since()
+                .zip(duration)
+                .and_then(|(since, duration)| since.checked_add_signed(duration))
+        }
+        StableFor::Checks(_) => None,
+    };
+
+    let next = [backoff::earliest_retry(), refresh, stable]
+        .into_iter()
+        .flatten()
+        .filter(|time| *time > now)
+        .fold(planned, DateTime::min);
+    let next = quiet_until(config, next).unwrap_or(next);
+    (next - now)
+        .to_std()
+        .unwrap_or_default()
+        .max(Duration::from_secs(1))
+}
@@ -1,94 +1,40 @@
-use log::{debug, error, warn};
+use chrono::{Local, Utc};
+use log::{debug, error, info, warn};
 use parking_lot::Mutex;
-use reqwest::{self, Client, ClientBuilder, Version, retry, tls};
+use reqwest::Version;
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
-use std::sync::LazyLock;
-use std::time::Duration;
-
-use crate::initialize::load_conf::{CONFIG, RecordType};
-
-static CLIENT: LazyLock<Client> = LazyLock::new(|| {
-    let time_out_secs = Duration::from_secs(5);
-    ClientBuilder::new()
-        .no_proxy()
-        .retry(retry::for_host("*").max_retries_per_request(3))
-        .https_only(true)
-        .http2_prior_knowledge()
-        .gzip(true)
-        .pool_idle_timeout(Duration::from_secs(180))
-        .connect_timeout(time_out_secs)
-        .read_timeout(time_out_secs)
-        .min_tls_version(tls::Version::TLS_1_3)
-        .build()
-        .unwrap()
-});
 
-static IPV4ADDR: Mutex<Ipv4Addr> = Mutex::new(Ipv4Addr::UNSPECIFIED);
-static IPV6ADDR: Mutex<Ipv6Addr> = Mutex::new(Ipv6Addr::UNSPECIFIED);
-
-async fn get_ip(ip_version: RecordType) -> Result<IpAddr, ()> {
-    let ip_version_u8 = ip_version.as_u8();
-    let get_ip_url = match ip_version {
-        RecordType::A => &CONFIG.get().unwrap().ipv4_url,
-        RecordType::AAAA => &CONFIG.get().unwrap().ipv6_url,
-    };
-    let ip_response = match CLIENT.get(get_ip_url.as_ref()).send().await {
-        Ok(success) => success,
-        Err(error) => {
-            if error.is_timeout() {
-                warn!("获取ipv{ip_version_u8}时链接超时",)
-            } else if error.is_connect() {
-                warn!("获取ipv{ip_version_u8}时链接错误{error}")
-            } else if error.is_builder() {
-                error!("获取ipv{ip_version_u8}的url不正确{error}");
-            } else {
-                warn!("获取ipv{ip_version_u8}时发生未定义错误{error}")
-            }
-            return Err(());
-        }
-    };
+use crate::cloudflare::{self, API_BASE};
+use crate::error::{ApiMessage, Error, NetworkKind};
+use crate::i18n;
+use crate::initialize::load_conf::{Config, RecordType, StableFor};
+use crate::obj::CLIENT;
+use crate::run::backoff;
+use crate::run::detect::get_ip;
+use crate::state;
 
-    if !ip_response.status().is_success() {
-        warn!(
-            "获取ipv{}时状态码不正确{}",
-            ip_version_u8,
-            ip_response.status().as_u16()
-        );
-        return Err(());
-    }
-
-    let ip_text = match ip_response.text().await {
-        Ok(success) => success,
-        Err(error) => {
-            warn!("获取IPv{}时响应正文时发生错误：{}", ip_version_u8, error);
-            return Err(());
-        }
-    };
-
-    let ip_text = &ip_version.re().captures(&ip_text).ok_or_else(|| {
-        warn!("无法从{get_ip_url}获取IPv{ip_version_u8}");
-    })?[0];
+#[derive(Clone, Copy, PartialEq)]
+pub enum UpdateMode {
+    Normal,
+    /// 只读取记录的当前状态并输出计划，不发送任何修改请求
+    DryRun,
+    /// 忽略 IP 缓存，无论是否变化都推送到记录
+    Force,
+    /// force_refresh 或重新加载后的一轮：推送所有记录，但新地址仍需等待 stable_for，等待期间推送已推送的地址
+    Refresh,
+}
 
-    match ip_version {
-        RecordType::A => match Ipv4Addr::from_str(ip_text) {
-            Ok(ip) => return Ok(IpAddr::V4(ip)),
-            Err(_) => {
-                warn!("获取到格式不正确的ipv4");
-                return Err(());
-            }
-        },
-        RecordType::AAAA => match Ipv6Addr::from_str(ip_text) {
-            Ok(ip) => return Ok(IpAddr::V6(ip)),
-            Err(_) => {
-                warn!("获取到格式不正确的ipv6");
-                return Err(());
-            }
-        },
+impl UpdateMode {
+    /// 是否修改缓存与状态文件
+    pub fn writes_state(self) -> bool {
+        self != UpdateMode::DryRun
     }
 }
 
-async fn ask_api(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), ()> {
+static IPV4ADDR: Mutex<Ipv4Addr> = Mutex::new(Ipv4Addr::UNSPECIFIED);
+static IPV6ADDR: Mutex<Ipv6Addr> = Mutex::new(Ipv6Addr::UNSPECIFIED);
+
+async fn ask_api(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), Error> {
     #[derive(Debug, serde::Serialize)]
     struct ApiBody<'a> {
         #[serde(rename = "type")]
@@ -98,6 +44,12 @@ async fn ask_api(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), ()
         proxied: bool,
         content: String,
     }
+    #[derive(serde::Deserialize)]
+    struct ApiErrorBody {
+        #[serde(default)]
+        errors: Vec<ApiMessage>,
+    }
+    let info = cloudflare::resolve(&info).await?;
     let json_body = ApiBody {
         record_type: info.record_type.as_str(),
         name: &info.name,
@@ -108,10 +60,10 @@ async fn ask_api(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), ()
 
     match CLIENT
         .put(format!(
-            "https://api.cloudflare.com/client/v4/zones/{}/dns_records/{}",
+            "{API_BASE}/zones/{}/dns_records/{}",
             info.zone_id, info.dns_id
         ))
-        .bearer_auth(&info.api_token)
+        .bearer_auth(info.api_token.expose())
         .json(&json_body)
         .header("Content-Type", "application/json")
         .send()
@@ -120,81 +72,265 @@ async fn ask_api(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), ()
         Ok(success) => {
             if success.status().is_success() {
                 debug_assert_eq!(success.version(), Version::HTTP_2);
-                debug!(" 成功: {}", serde_json::to_string(&json_body).unwrap());
-            } else {
-                warn!(
-                    "更新:{},类型:{}时服务器返回码:{}",
-                    json_body.name,
-                    json_body.record_type,
-                    success.status().as_u16()
+                debug!(
+                    "{}",
+                    i18n::update_success(&serde_json::to_string(&json_body).unwrap())
                 );
-                return Err(());
+            } else {
+                let status = success.status().as_u16();
+                let errors = success
+                    .json::<ApiErrorBody>()
+                    .await
+                    .map(|body| body.errors)
+                    .unwrap_or_default();
+                return Err(Error::Api {
+                    status,
+                    errors,
+                    message: i18n::update_bad_status(json_body.name, json_body.record_type, status),
+                });
             }
         }
         Err(error) => {
             debug!("{error}");
-            if error.is_timeout() {
-                warn!(
-                    "更新:{},类型:{}时链接超时",
-                    json_body.name, json_body.record_type
-                );
+            return Err(if error.is_timeout() {
+                Error::Network {
+                    kind: NetworkKind::Timeout,
+                    message: i18n::update_timeout(json_body.name, json_body.record_type),
+                }
             } else if error.is_connect() {
-                warn!(
-                    "更新:{},类型:{}时链接错误",
-                    json_body.name, json_body.record_type
-                );
+                Error::Network {
+                    kind: NetworkKind::Connect,
+                    message: i18n::update_connect_error(json_body.name, json_body.record_type),
+                }
             } else {
-                warn!(
-                    "更新{}类型{}时发生未知错误:{}",
-                    json_body.name, json_body.record_type, error
-                );
-            }
-            return Err(());
+                Error::Network {
+                    kind: NetworkKind::Other,
+                    message: i18n::update_unknown_error(
+                        json_body.name,
+                        json_body.record_type,
+                        error,
+                    ),
+                }
+            });
         }
     };
     Ok(())
 }
 
-pub async fn update_ip(ip_version: RecordType, config_json: Vec<&crate::load_conf::DnsRecord>) {
+/// 读取记录当前状态，输出与 ask_api 将要提交的内容之间的差异
+async fn plan(ip: IpAddr, info: crate::load_conf::DnsRecord) -> Result<(), Error> {
+    let info = cloudflare::resolve(&info).await?;
+    let remote = cloudflare::get_record(info.api_token.expose(), &info.zone_id, &info.dns_id).await?;
+
+    let content = ip.to_string();
+    let mut changes = Vec::new();
+    if remote.content != content {
+        changes.push(i18n::plan_content(&remote.content, &content));
+    }
+    if !remote.name.eq_ignore_ascii_case(info.name.trim_end_matches('.')) {
+        changes.push(i18n::plan_name(&remote.name, &info.name));
+    }
+    if remote.ttl != info.ttl {
+        changes.push(i18n::plan_ttl(remote.ttl, info.ttl));
+    }
+    if remote.proxied != info.proxied {
+        changes.push(i18n::plan_proxied(remote.proxied, info.proxied));
+    }
+
+    // 计划是 --dry-run 的输出，不受 log_level 影响，也不带日志前缀
+    let record_type = info.record_type.as_str();
+    if changes.is_empty() {
+        println!("{}", i18n::plan_unchanged(record_type, &info.name, &content));
+    } else {
+        println!(
+            "{}",
+            i18n::plan_changed(record_type, &info.name, &changes.join(", "))
+        );
+    }
+    Ok(())
+}
+
+fn log_error(e: &Error) {
+    if e.is_transient() {
+        warn!("{e}");
+    } else {
+        error!("{e}");
+    }
+}
+
+/// 本轮要推送的地址，新地址仍在等待 stable_for 时返回 None
+///
+/// 与已推送的地址不同的新地址需要稳定一段时间才推送，只有 update --force 与启动后的第一轮不等待；
+/// 刷新轮在等待期间改为推送已推送的地址
+fn address_to_push(ip: IpAddr, mode: UpdateMode, stable_for: StableFor) -> Option<IpAddr> {
+    let published = match ip {
+        IpAddr::V4(_) => IpAddr::V4(*IPV4ADDR.lock()),
+        IpAddr::V6(_) => IpAddr::V6(*IPV6ADDR.lock()),
+    };
+    let waits = matches!(mode, UpdateMode::Normal | UpdateMode::Refresh);
+    if !waits || published.is_unspecified() || published == ip {
+        if mode.writes_state() {
+            state::clear_pending(ip);
+        }
+        return Some(ip);
+    }
+    let Some(pending) = state::observe(ip, stable_for) else {
+        return Some(ip);
+    };
+    info!(
+        "{}",
+        i18n::ip_pending(
+            if ip.is_ipv4() { 4 } else { 6 },
+            pending.address,
+            pending.checks,
+            (Utc::now() - pending.since).num_seconds()
+        )
+    );
+    (mode == UpdateMode::Refresh).then_some(published)
+}
+
+/// 返回本轮遇到的第一个错误，所有错误都已在内部记录日志
+pub async fn update_ip(
+    ip_version: RecordType,
+    config_json: Vec<crate::load_conf::DnsRecord>,
+    mode: UpdateMode,
+) -> Result<(), Error> {
     if config_json.is_empty() {
-        debug!("没有需要更新的{}记录", ip_version.as_str());
-        return;
+        debug!("{}", i18n::no_records(ip_version.as_str()));
+        return Ok(());
     }
 
     let ip = match get_ip(ip_version).await {
         Ok(success) => {
-            debug!("获取成功，当前IPv{}地址为：{}", ip_version.as_u8(), success);
-            success
+            debug!("{}", i18n::get_ip_success(ip_version.as_u8(), success.ip));
+            if mode.writes_state() {
+                state::detected(success.ip, &success.source);
+            }
+            success.ip
+        }
+        Err(e) => {
+            log_error(&e);
+            return Err(e);
         }
-        Err(_) => return,
     };
 
-    // 检查IP是否变化
-    match ip {
+    let Some(ip) = address_to_push(ip, mode, Config::current()?.stable_for) else {
+        return Ok(());
+    };
+
+    // 检查IP是否变化，dry run 不修改缓存
+    let changed = match ip {
         IpAddr::V4(ipv4) => {
             let mut ipv4_inner = IPV4ADDR.lock();
-            if ipv4 == *ipv4_inner {
-                debug!("IPv4地址未改变，跳过更新");
-                return;
-            } else {
+            let changed = ipv4 != *ipv4_inner;
+            if mode.writes_state() {
                 *ipv4_inner = ipv4;
             }
+            changed
         }
         IpAddr::V6(ipv6) => {
             let mut ipv6_inner = IPV6ADDR.lock();
-            if ipv6 == *ipv6_inner {
-                debug!("IPv6地址未改变，跳过更新");
-                return;
-            } else {
+            let changed = ipv6 != *ipv6_inner;
+            if mode.writes_state() {
                 *ipv6_inner = ipv6;
             }
+            changed
         }
+    };
+    if !changed {
+        debug!("{}", i18n::ip_unchanged(ip_version.as_u8()));
     }
-    let mut task_set = tokio::task::JoinSet::new();
 
-    config_json.iter().for_each(|&i| {
-        task_set.spawn(ask_api(ip, i.clone()));
+    // IP 未变化时只重试上次失败的记录，退避中的记录等到重试时间再请求；强制更新不受影响
+    let records: Vec<_> = config_json
+        .into_iter()
+        .filter(|record| {
+            if matches!(mode, UpdateMode::Force | UpdateMode::Refresh) {
+                return true;
+            }
+            if let Some(retry_at) = state::retry_waiting(record) {
+                debug!(
+                    "{}",
+                    i18n::record_backing_off(
+                        &record.name,
+                        record.record_type.as_str(),
+                        retry_at.with_timezone(&Local).format("%H:%M:%S")
+                    )
+                );
+                return false;
+            }
+            changed || state::needs_retry(record)
+        })
+        .collect();
+    if records.is_empty() {
+        return Ok(());
+    }
+
+    let config = Config::current()?;
+    let mut task_set = tokio::task::JoinSet::new();
+    records.into_iter().for_each(|record| match mode {
+        UpdateMode::Normal | UpdateMode::Force | UpdateMode::Refresh => {
+            let config = config.clone();
+            task_set.spawn(async move {
+                let result = ask_api(ip, record.clone()).await;
+                let retry_at = state::record_result(&record, &ip.to_string(), &result, |failures| {
+                    backoff::retry_at(failures, &config)
+                });
+                if let Some(retry_at) = retry_at {
+                    info!(
+                        "{}",
+                        i18n::record_retry_scheduled(
+                            &record.name,
+                            record.record_type.as_str(),
+                            retry_at.with_timezone(&Local).format("%H:%M:%S")
+                        )
+                    );
+                }
+                result
+            });
+        }
+        UpdateMode::DryRun => {
+            task_set.spawn(plan(ip, record));
+        }
     });
 
-    let _a = task_set.join_all().await;
+    let errors: Vec<Error> = task_set
+        .join_all()
+        .await
+        .into_iter()
+        .filter_map(Result::err)
+        .collect();
+    errors.iter().for_each(log_error);
+    errors.into_iter().next().map_or(Ok(()), Err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refresh_round_keeps_pending_address() {
+        let published = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1);
+        let new = IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 2));
+        *IPV6ADDR.lock() = published;
+        let stable_for = StableFor::Checks(3);
+
+        // 刷新轮推送已推送的地址，而不是等待中的新地址
+        assert_eq!(
+            address_to_push(new, UpdateMode::Refresh, stable_for),
+            Some(IpAddr::V6(published))
+        );
+        assert_eq!(address_to_push(new, UpdateMode::Normal, stable_for), None);
+        // 第三次检测到后稳定，刷新轮推送新地址
+        assert_eq!(
+            address_to_push(new, UpdateMode::Refresh, stable_for),
+            Some(new)
+        );
+        // 只有 update --force 不等待
+        *IPV6ADDR.lock() = published;
+        assert_eq!(
+            address_to_push(new, UpdateMode::Force, stable_for),
+            Some(new)
+        );
+    }
 }
@@ -0,0 +1,97 @@
+use std::fmt::{self, Debug, Display};
+use zeroize::Zeroize;
+
+/// API Token 等敏感字符串，Debug 与 Display 只输出掩码，释放时清零内存
+///
+/// 需要原文时调用 [`Secret::expose`]，序列化会写出原文以便保存配置文件
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn new(value: impl Into<String>) -> Self {
+        Secret(value.into())
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Display for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("********")
+    }
+}
+
+impl Debug for Secret {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({self})")
+    }
+}
+
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl serde::Serialize for Secret {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> serde::Deserialize<'de> for Secret {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Secret)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Secret;
+    use crate::initialize::load_conf::Config;
+
+    const TOKEN: &str = "cf-test-token-0123456789abcdef";
+
+    fn config() -> Config {
+        toml::from_str(&format!(
+            r#"
+            [[dns_records]]
+            api_token = "{TOKEN}"
+            zone_id = "0123456789abcdef0123456789abcdef"
+            dns_id = "fedcba9876543210fedcba9876543210"
+            type = "A"
+            name = "example.com"
+            ttl = 1
+            proxied = false
+            "#
+        ))
+        .unwrap()
+    }
+
+    #[test]
+    fn formatting_never_contains_token() {
+        let config = config();
+        let record = &config.dns_records[0];
+        assert_eq!(record.api_token.expose(), TOKEN);
+
+        let outputs = [
+            format!("{config:?}"),
+            format!("{config:#?}"),
+            format!("{record:?}"),
+            format!("{}", record.api_token),
+            format!("{:?}", record.api_token),
+            format!("token: {}", Secret::new(TOKEN)),
+        ];
+        for output in outputs {
+            assert!(!output.contains(TOKEN), "token leaked in {output}");
+        }
+    }
+
+    #[test]
+    fn serialization_keeps_token() {
+        let config = config();
+        assert!(toml::to_string(&config).unwrap().contains(TOKEN));
+    }
+}
@@ -1,7 +1,10 @@
 use std::env::current_exe;
+
+use crate::error::Error;
+use crate::i18n;
 use std::process;
 
-pub fn service() -> Result<(), String> {
+pub fn service() -> Result<(), Error> {
     if cfg!(windows) {
         let (service_name, start_type, service_command) = if cfg!(debug_assertions) {
             (
@@ -32,10 +35,10 @@ pub fn service() -> Result<(), String> {
                 service_command.as_str(),
             ])
             .status()
-            .map_err(|e| format!("创建服务失败，回溯错误：{e}"))?
+            .map_err(|e| Error::Install(i18n::service_create_failed(e)))?
             .success()
-            .then(|| ())
-            .ok_or("创建服务失败，请检查是否有管理员权限")?;
+            .then_some(())
+            .ok_or_else(|| Error::Install(i18n::service_create_need_admin()))?;
     } else if cfg!(unix) {
         let service_file = concat!(
             "[Unit]\n",
@@ -44,6 +47,7 @@ pub fn service() -> Result<(), String> {
             "[Service]\n",
             "Type=simple\n",
             "ExecStart={} run --loops\n",
+            "StateDirectory=ddns_rust\n",
             "Restart=on-failure\n",
             "KillSignal=SIGINT\n",
             "TimeoutStopSec=20\n\n",
@@ -53,12 +57,12 @@ pub fn service() -> Result<(), String> {
         .replace("{}", current_exe().unwrap().to_str().unwrap());
 
         std::fs::write("/etc/systemd/system/cloudflareddns.service", service_file)
-            .map_err(|e| format!("创建服务失败，请检查是否有管理员权限，回溯错误：{e}"))?;
+            .map_err(|e| Error::Install(i18n::systemd_service_write_failed(e)))?;
     }
     Ok(())
 }
 
-pub fn schedule() -> Result<(), String> {
+pub fn schedule() -> Result<(), Error> {
     if cfg!(windows) {
         process::Command::new("schtasks")
             .args([
@@ -79,16 +83,17 @@ pub fn schedule() -> Result<(), String> {
                 "System",
             ])
             .status()
-            .map_err(|e| format!("创建计划任务失败，回溯错误：{e}"))?
+            .map_err(|e| Error::Install(i18n::schedule_create_failed(e)))?
             .success()
-            .then(|| ())
-            .ok_or("创建计划任务失败，请检查是否有管理员权限")?;
+            .then_some(())
+            .ok_or_else(|| Error::Install(i18n::schedule_create_need_admin()))?;
     } else if cfg!(unix) {
         let service_file = concat!(
             "[Unit]\n",
             "Description=CloudflareDDNS Once Service\n\n",
             "[Service]\n",
             "Type=oneshot\n",
+            "StateDirectory=ddns_rust\n",
             "ExecStart={} run --once",
         )
         .replace("{}", current_exe().unwrap().to_str().unwrap());
@@ -109,13 +114,13 @@ pub fn schedule() -> Result<(), String> {
                 "/etc/systemd/system/cloudflareddns.timer",
                 timer_file,
             ))
-            .map_err(|e| format!("创建systemd timer失败，请检查是否有管理员权限，回溯错误：{e}"))?;
+            .map_err(|e| Error::Install(i18n::systemd_timer_write_failed(e)))?;
     }
     Ok(())
 }
 
 #[cfg(unix)]
-pub fn cron() -> Result<(), String> {
+pub fn cron() -> Result<(), Error> {
     let cron_job = format!(
         "*/2 * * * * {} run --once\n",
         std::env::current_exe().unwrap().display()
@@ -127,6 +132,51 @@ pub fn cron() -> Result<(), String> {
             cron_job
         ))
         .status()
-        .map_err(|e| format!("创建计划任务失败，回溯错误：{e}"))?;
+        .map_err(|e| Error::Install(i18n::schedule_create_failed(e)))?;
+    Ok(())
+}
+
+/// 各 shell 在系统范围内自动加载补全脚本的位置
+#[cfg(unix)]
+pub const COMPLETION_PATHS: [(clap_complete::Shell, &str); 3] = [
+    (
+        clap_complete::Shell::Bash,
+        "/usr/share/bash-completion/completions/ddns_rust",
+    ),
+    (
+        clap_complete::Shell::Zsh,
+        "/usr/share/zsh/site-functions/_ddns_rust",
+    ),
+    (
+        clap_complete::Shell::Fish,
+        "/usr/share/fish/vendor_completions.d/ddns_rust.fish",
+    ),
+];
+
+#[cfg(unix)]
+pub const MANPAGE_PATH: &str = "/usr/share/man/man1/ddns_rust.1";
+
+#[cfg(unix)]
+fn write_generated(path: &str, content: &[u8]) -> Result<(), Error> {
+    let path_ref = std::path::Path::new(path);
+    if let Some(parent) = path_ref.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| Error::Install(i18n::generated_write_failed(path, e)))?;
+    }
+    std::fs::write(path_ref, content)
+        .map_err(|e| Error::Install(i18n::generated_write_failed(path, e)))?;
+    println!("{}", i18n::generated_installed(path));
     Ok(())
 }
+
+#[cfg(unix)]
+pub fn completions() -> Result<(), Error> {
+    COMPLETION_PATHS.iter().try_for_each(|(shell, path)| {
+        write_generated(path, &crate::tools::generate::render_completions(*shell))
+    })
+}
+
+#[cfg(unix)]
+pub fn manpage() -> Result<(), Error> {
+    write_generated(MANPAGE_PATH, &crate::tools::generate::render_manpage()?)
+}
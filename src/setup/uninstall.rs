@@ -1,44 +1,69 @@
 use std::process;
 
-pub fn service() -> Result<(), String> {
+use crate::error::Error;
+use crate::i18n;
+
+pub fn service() -> Result<(), Error> {
     if cfg!(windows) {
         process::Command::new("sc")
             .args(["delete", "CloudflareDDNS"])
             .status()
-            .map_err(|e| format!("删除服务失败，回溯错误：{e}"))?
+            .map_err(|e| Error::Install(i18n::service_delete_failed(e)))?
             .success()
-            .then(|| ())
-            .ok_or("删除服务失败，请检查是否有管理员权限")?;
+            .then_some(())
+            .ok_or_else(|| Error::Install(i18n::service_delete_need_admin()))?;
     } else if cfg!(unix) {
         std::fs::remove_file("/etc/systemd/system/cloudflareddns.service")
-            .map_err(|e| format!("删除服务文件失败，请检查是否有管理员权限，回溯错误：{e}"))?;
+            .map_err(|e| Error::Install(i18n::systemd_service_delete_failed(e)))?;
     }
     Ok(())
 }
 
-pub fn schedule() -> Result<(), String> {
+pub fn schedule() -> Result<(), Error> {
     if cfg!(windows) {
         process::Command::new("schtasks")
             .args(["/delete", "/tn", "CloudflareDDNS", "/f"])
             .status()
-            .map_err(|e| format!("删除计划任务失败，回溯错误：{e}"))?
+            .map_err(|e| Error::Install(i18n::schedule_delete_failed(e)))?
             .success()
             .then_some(())
-            .ok_or("删除计划任务失败，请检查是否有管理员权限".to_string())?;
+            .ok_or_else(|| Error::Install(i18n::schedule_delete_need_admin()))?;
     } else if cfg!(unix) {
         std::fs::remove_file("/etc/systemd/system/cloudflareddns.service").and(
             std::fs::remove_file("/etc/systemd/system/cloudflareddns.timer"),
-        ).map_err(|e| format!("删除systemd timer失败，请检查是否有管理员权限，回溯错误：{e}"))?;
+        ).map_err(|e| Error::Install(i18n::systemd_timer_delete_failed(e)))?;
     }
     Ok(())
 }
 
 #[cfg(unix)]
-pub fn cron() -> Result<(), String> {
+pub fn cron() -> Result<(), Error> {
     process::Command::new("sh")
         .arg("-c")
         .arg("crontab -l 2>/dev/null | grep -v \"run --once\" | crontab -")
         .status()
-        .map_err(|e| format!("删除计划任务失败，回溯错误：{e}"))?;
+        .map_err(|e| Error::Install(i18n::schedule_delete_failed(e)))?;
     Ok(())
 }
+
+#[cfg(unix)]
+fn remove_generated(path: &str) -> Result<(), Error> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(Error::Install(i18n::generated_delete_failed(path, e)))
+        }
+        _ => Ok(()),
+    }
+}
+
+#[cfg(unix)]
+pub fn completions() -> Result<(), Error> {
+    super::install::COMPLETION_PATHS
+        .iter()
+        .try_for_each(|(_, path)| remove_generated(path))
+}
+
+#[cfg(unix)]
+pub fn manpage() -> Result<(), Error> {
+    remove_generated(super::install::MANPAGE_PATH)
+}
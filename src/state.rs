@@ -0,0 +1,265 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use log::{debug, warn};
+use parking_lot::Mutex;
+use std::fs;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::path::PathBuf;
+
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::{DnsRecord, RecordType, StableFor};
+use crate::obj::DATA_DIR;
+
+/// 运行中的状态，每轮更新结束后写入 state.json 供 status 命令读取
+pub static STATE: Mutex<State> = Mutex::new(State::new());
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct Detection {
+    pub address: IpAddr,
+    pub source: String,
+    pub time: DateTime<Utc>,
+}
+
+/// 新检测到但还未稳定、尚未推送的地址
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct Pending {
+    pub address: IpAddr,
+    /// 第一次检测到的时间
+    pub since: DateTime<Utc>,
+    /// 连续检测到的次数
+    pub checks: u32,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone)]
+pub struct RecordState {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub record_type: RecordType,
+    /// 最后一次成功推送的内容
+    pub content: Option<String>,
+    pub last_success: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub last_error_time: Option<DateTime<Utc>>,
+    pub consecutive_failures: u32,
+    /// 失败后退避，在此时间之前不会再次请求
+    #[serde(default)]
+    pub next_retry: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, serde::Deserialize, serde::Serialize, Clone, Default)]
+pub struct State {
+    pub ipv4: Option<Detection>,
+    pub ipv6: Option<Detection>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv4_pending: Option<Pending>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6_pending: Option<Pending>,
+    #[serde(default)]
+    pub records: Vec<RecordState>,
+}
+
+impl State {
+    const fn new() -> State {
+        State {
+            ipv4: None,
+            ipv6: None,
+            ipv4_pending: None,
+            ipv6_pending: None,
+            records: Vec::new(),
+        }
+    }
+
+    pub fn path() -> PathBuf {
+        DATA_DIR.join("state.json")
+    }
+
+    /// 状态文件不存在时返回空状态
+    pub fn read() -> Result<State, Error> {
+        let path = State::path();
+        match fs::read(&path) {
+            Ok(content) => serde_json::from_slice(&content)
+                .map_err(|e| Error::Runtime(i18n::state_invalid(path.display(), e))),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(State::default()),
+            Err(e) => Err(Error::Runtime(i18n::state_read_failed(path.display(), e))),
+        }
+    }
+
+    /// 先写入临时文件再重命名，避免 status 读到写了一半的文件
+    fn write(&self) -> Result<(), Error> {
+        let path = State::path();
+        let temp_path = path.with_extension("json.tmp");
+        let content = serde_json::to_vec_pretty(self)
+            .map_err(|e| Error::Runtime(i18n::state_write_failed(path.display(), e)))?;
+        fs::write(&temp_path, content)
+            .and_then(|_| fs::rename(&temp_path, &path))
+            .map_err(|e| Error::Runtime(i18n::state_write_failed(path.display(), e)))
+    }
+
+    fn pending_mut(&mut self, ip: IpAddr) -> &mut Option<Pending> {
+        match ip {
+            IpAddr::V4(_) => &mut self.ipv4_pending,
+            IpAddr::V6(_) => &mut self.ipv6_pending,
+        }
+    }
+
+    fn record_mut(&mut self, record: &DnsRecord) -> &mut RecordState {
+        let index = match self.records.iter().position(|state| {
+            state.record_type == record.record_type && state.name == record.name
+        }) {
+            Some(index) => index,
+            None => {
+                self.records.push(RecordState {
+                    name: record.name.clone(),
+                    record_type: record.record_type,
+                    content: None,
+                    last_success: None,
+                    last_error: None,
+                    last_error_time: None,
+                    consecutive_failures: 0,
+                    next_retry: None,
+                });
+                self.records.len() - 1
+            }
+        };
+        &mut self.records[index]
+    }
+}
+
+/// 读取上次运行留下的状态，使连续失败次数等信息在单次运行之间保留
+pub fn load() {
+    match State::read() {
+        Ok(state) => *STATE.lock() = state,
+        Err(e) => warn!("{e}"),
+    }
+}
+
+pub fn save() {
+    let state = STATE.lock().clone();
+    match state.write() {
+        Ok(()) => debug!("{}", i18n::state_saved()),
+        Err(e) => warn!("{e}"),
+    }
+}
+
+pub fn detected(ip: IpAddr, source: &str) {
+    let detection = Some(Detection {
+        address: ip,
+        source: source.to_string(),
+        time: Utc::now(),
+    });
+    let mut state = STATE.lock();
+    match ip {
+        IpAddr::V4(_) => state.ipv4 = detection,
+        IpAddr::V6(_) => state.ipv6 = detection,
+    }
+}
+
+/// 记录又一次检测到与已推送不同的地址，地址变化时重新计算
+///
+/// 已经稳定时清除并返回 None，否则返回仍在等待的地址
+pub fn observe(ip: IpAddr, stable_for: StableFor) -> Option<Pending> {
+    let now = Utc::now();
+    let mut state = STATE.lock();
+    let pending = state.pending_mut(ip);
+    match pending {
+        Some(pending) if pending.address == ip => pending.checks += 1,
+        _ => {
+            *pending = Some(Pending {
+                address: ip,
+                since: now,
+                checks: 1,
+            })
+        }
+    }
+    let stable = pending.as_ref().is_some_and(|pending| match stable_for {
+        StableFor::Checks(checks) => pending.checks >= checks,
+        StableFor::Seconds(seconds) => i64::try_from(seconds)
+            .ok()
+            .and_then(TimeDelta::try_seconds)
+            .is_some_and(|duration| now - pending.since >= duration),
+    });
+    if stable {
+        *pending = None;
+    }
+    pending.clone()
+}
+
+/// 检测到的地址与已推送的相同，或者不需要等待时放弃等待中的地址
+pub fn clear_pending(ip: IpAddr) {
+    *STATE.lock().pending_mut(ip) = None;
+}
+
+/// 等待中的地址最早第一次检测到的时间
+pub fn pending_since() -> Option<DateTime<Utc>> {
+    let state = STATE.lock();
+    [&state.ipv4_pending, &state.ipv6_pending]
+        .into_iter()
+        .flatten()
+        .map(|pending| pending.since)
+        .min()
+}
+
+/// 失败时按连续失败次数计算并返回下次重试的时间
+pub fn record_result(
+    record: &DnsRecord,
+    content: &str,
+    result: &Result<(), Error>,
+    retry_at: impl FnOnce(u32) -> DateTime<Utc>,
+) -> Option<DateTime<Utc>> {
+    let mut state = STATE.lock();
+    let record_state = state.record_mut(record);
+    match result {
+        Ok(()) => {
+            record_state.content = Some(content.to_string());
+            record_state.last_success = Some(Utc::now());
+            record_state.consecutive_failures = 0;
+            record_state.next_retry = None;
+        }
+        Err(e) => {
+            record_state.last_error = Some(e.to_string());
+            record_state.last_error_time = Some(Utc::now());
+            record_state.consecutive_failures += 1;
+            record_state.next_retry = Some(retry_at(record_state.consecutive_failures));
+        }
+    }
+    record_state.next_retry
+}
+
+fn find<T>(record: &DnsRecord, f: impl FnOnce(&RecordState) -> T) -> Option<T> {
+    STATE
+        .lock()
+        .records
+        .iter()
+        .find(|state| state.record_type == record.record_type && state.name == record.name)
+        .map(f)
+}
+
+/// 上次推送失败，即使 IP 没有变化也需要重试
+pub fn needs_retry(record: &DnsRecord) -> bool {
+    find(record, |state| state.consecutive_failures > 0).unwrap_or(false)
+}
+
+/// 记录仍在退避中时返回可以重试的时间
+pub fn retry_waiting(record: &DnsRecord) -> Option<DateTime<Utc>> {
+    find(record, |state| state.next_retry)
+        .flatten()
+        .filter(|retry_at| *retry_at > Utc::now())
+}
+
+pub fn retry_times() -> Vec<DateTime<Utc>> {
+    STATE
+        .lock()
+        .records
+        .iter()
+        .filter_map(|state| state.next_retry)
+        .collect()
+}
+
+pub fn clear_retries() {
+    STATE
+        .lock()
+        .records
+        .iter_mut()
+        .for_each(|state| state.next_retry = None);
+}
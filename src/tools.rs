@@ -0,0 +1,32 @@
+pub mod check;
+#[cfg(unix)]
+pub mod ctl;
+pub mod generate;
+pub mod ip;
+pub mod records;
+pub mod status;
+pub mod wizard;
+
+/// 按列宽左对齐输出表格
+pub fn print_table(indent: &str, headers: &[&str], rows: &[Vec<String>]) {
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .chain([headers[i].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let print_row = |cells: Vec<&str>| {
+        let line: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect();
+        println!("{indent}{}", line.join("  ").trim_end());
+    };
+    print_row(headers.to_vec());
+    rows.iter()
+        .for_each(|row| print_row(row.iter().map(String::as_str).collect()));
+}
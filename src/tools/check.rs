@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+
+use crate::cloudflare;
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::{CONFIG_WARNINGS, Config, Problem};
+
+pub fn check(online: bool) -> Result<(), Error> {
+    let config = Config::current()?;
+    let config = config.as_ref();
+    println!("{}", Config::source());
+    CONFIG_WARNINGS
+        .get()
+        .into_iter()
+        .flatten()
+        .for_each(|warning| println!("[WARN] {warning}"));
+    println!();
+
+    let mut problems = config.validate();
+    if online {
+        let online_problems = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|e| Error::Runtime(i18n::runtime_create_failed(e)))?
+            .block_on(check_online(config));
+        problems.extend(online_problems);
+    }
+
+    print_report(config, &problems);
+    if problems.is_empty() {
+        println!("{}", i18n::check_passed(config.dns_records.len()));
+        Ok(())
+    } else {
+        Err(Error::Config(i18n::check_failed(problems.len())))
+    }
+}
+
+async fn check_online(config: &Config) -> Vec<Problem> {
+    let mut problems = Vec::new();
+    // 同一个 token / zone 只请求一次
+    let mut tokens: HashMap<&str, Result<(), String>> = HashMap::new();
+    let mut zones: HashMap<(String, String), Result<String, String>> = HashMap::new();
+
+    for (i, record) in config.dns_records.iter().enumerate() {
+        let mut push = |message| {
+            problems.push(Problem {
+                record: Some(i),
+                message,
+            })
+        };
+
+        let token = record.api_token.expose();
+        if let Entry::Vacant(entry) = tokens.entry(token) {
+            entry.insert(match cloudflare::verify_token(token).await {
+                Ok(status) if status.status == "active" => Ok(()),
+                Ok(status) => Err(i18n::token_inactive(&status.status)),
+                Err(e) => Err(i18n::token_verify_failed(e)),
+            });
+        }
+        if let Err(message) = &tokens[token] {
+            push(message.clone());
+            continue;
+        }
+
+        // 未填写 ID 的记录先按名称查找
+        let record = match cloudflare::resolve(record).await {
+            Ok(record) => record,
+            Err(e) => {
+                push(e.to_string());
+                continue;
+            }
+        };
+
+        let zone_key = (token.to_string(), record.zone_id.clone());
+        if let Entry::Vacant(entry) = zones.entry(zone_key.clone()) {
+            entry.insert(
+                cloudflare::get_zone(token, &record.zone_id)
+                    .await
+                    .map(|zone| zone.name)
+                    .map_err(i18n::zone_lookup_failed),
+            );
+        }
+        match &zones[&zone_key] {
+            Ok(zone_name) => {
+                let name = record.name.trim_end_matches('.').to_ascii_lowercase();
+                let zone_name = zone_name.to_ascii_lowercase();
+                if name != zone_name && !name.ends_with(&format!(".{zone_name}")) {
+                    push(i18n::name_outside_zone(&record.name, &zone_name));
+                }
+            }
+            Err(message) => {
+                push(message.clone());
+                continue;
+            }
+        }
+
+        match cloudflare::get_record(token, &record.zone_id, &record.dns_id).await {
+            Ok(remote) => {
+                if !remote
+                    .name
+                    .eq_ignore_ascii_case(record.name.trim_end_matches('.'))
+                {
+                    push(i18n::record_name_mismatch(&record.name, &remote.name));
+                }
+                if remote.record_type != record.record_type.as_str() {
+                    push(i18n::record_type_mismatch(
+                        record.record_type.as_str(),
+                        &remote.record_type,
+                    ));
+                }
+            }
+            Err(e) => push(i18n::record_lookup_failed(e)),
+        }
+    }
+    problems
+}
+
+fn print_report(config: &Config, problems: &[Problem]) {
+    problems
+        .iter()
+        .filter(|problem| problem.record.is_none())
+        .for_each(|problem| println!("[FAIL] {}: {}", i18n::check_global(), problem.message));
+
+    for (i, record) in config.dns_records.iter().enumerate() {
+        let record_problems: Vec<&Problem> = problems
+            .iter()
+            .filter(|problem| problem.record == Some(i))
+            .collect();
+        let tag = if record_problems.is_empty() {
+            "[ OK ]"
+        } else {
+            "[FAIL]"
+        };
+        println!("{tag} #{i} {} {}", record.record_type.as_str(), record.name);
+        record_problems
+            .iter()
+            .for_each(|problem| println!("       - {}", problem.message));
+    }
+}
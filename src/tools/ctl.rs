@@ -0,0 +1,43 @@
+use std::io::{Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::parse_args::CtlAction;
+use crate::run::control::socket_path;
+
+/// 发送给控制 socket 的命令，与 run::control 中的匹配保持一致
+fn command(action: CtlAction) -> &'static str {
+    match action {
+        CtlAction::Pause => "pause",
+        CtlAction::Resume => "resume",
+        CtlAction::Trigger => "trigger",
+        CtlAction::Status => "status",
+        CtlAction::Reload => "reload",
+    }
+}
+
+pub fn ctl(action: CtlAction) -> Result<(), Error> {
+    let path = socket_path();
+    let mut stream = UnixStream::connect(&path)
+        .map_err(|e| Error::Runtime(i18n::ctl_connect_failed(path.display(), e)))?;
+    let io_error = |e: std::io::Error| Error::Runtime(i18n::ctl_io_failed(e));
+
+    stream
+        .set_read_timeout(Some(Duration::from_secs(30)))
+        .map_err(io_error)?;
+    writeln!(stream, "{}", command(action)).map_err(io_error)?;
+    let mut reply = String::new();
+    stream.read_to_string(&mut reply).map_err(io_error)?;
+
+    let (status, message) = reply.split_once('\n').unwrap_or((reply.as_str(), ""));
+    let message = message.trim_end();
+    match status {
+        "ok" => {
+            println!("{message}");
+            Ok(())
+        }
+        _ => Err(Error::Runtime(message.to_string())),
+    }
+}
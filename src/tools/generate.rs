@@ -0,0 +1,45 @@
+use clap::CommandFactory;
+use clap_complete::Shell;
+use std::io::{self, Write};
+
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::parse_args::CliArgs;
+
+pub const BIN_NAME: &str = "ddns_rust";
+
+/// 生成文件中使用可执行文件名，而不是显示用的 "Cloudflare DDNS"
+fn command() -> clap::Command {
+    CliArgs::command()
+        .name(BIN_NAME)
+        .bin_name(BIN_NAME)
+        .version(env!("CARGO_PKG_VERSION"))
+}
+
+pub fn render_completions(shell: Shell) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    clap_complete::generate(shell, &mut command(), BIN_NAME, &mut buffer);
+    buffer
+}
+
+pub fn render_manpage() -> Result<Vec<u8>, Error> {
+    let mut buffer = Vec::new();
+    clap_mangen::Man::new(command())
+        .render(&mut buffer)
+        .map_err(|e| Error::Runtime(i18n::manpage_render_failed(e)))?;
+    Ok(buffer)
+}
+
+fn print(content: &[u8]) -> Result<(), Error> {
+    io::stdout()
+        .write_all(content)
+        .map_err(|e| Error::Runtime(i18n::output_write_failed(e)))
+}
+
+pub fn completions(shell: Shell) -> Result<(), Error> {
+    print(&render_completions(shell))
+}
+
+pub fn manpage() -> Result<(), Error> {
+    print(&render_manpage()?)
+}
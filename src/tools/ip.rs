@@ -0,0 +1,83 @@
+use std::time::Instant;
+
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::{Config, RecordType};
+use crate::run::{detect, filter};
+
+/// 原始响应最多输出的字符数
+const RAW_LIMIT: usize = 200;
+
+async fn detect_all(ip_versions: &[RecordType]) -> Vec<Error> {
+    let mut errors = Vec::new();
+    for &ip_version in ip_versions {
+        println!("IPv{}", ip_version.as_u8());
+        let sources = match detect::sources(ip_version) {
+            Ok(sources) => sources,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+        for source in &sources {
+            println!("  source:  {source}");
+            if let detect::Source::Http { parser, .. } = source {
+                println!("  parser:  {parser}");
+            }
+            let start = Instant::now();
+            let fetched = detect::fetch(ip_version, source).await;
+            println!("  time:    {} ms", start.elapsed().as_millis());
+
+            let result = fetched.and_then(|text| {
+                let raw: String = text.chars().take(RAW_LIMIT).collect();
+                if text.chars().count() > RAW_LIMIT {
+                    println!("  raw:     {raw:?}...");
+                } else {
+                    println!("  raw:     {raw:?}");
+                }
+                detect::extract(ip_version, source, &text)
+            });
+            match result {
+                Ok(ip) => {
+                    println!("  address: {ip}");
+                    match Config::current().and_then(|config| filter::check(ip, &config)) {
+                        Ok(()) if filter::is_cgnat(ip) => {
+                            println!("  warning: {}", i18n::address_cgnat(ip));
+                        }
+                        Ok(()) => (),
+                        Err(e) => {
+                            println!("  error:   {e}");
+                            errors.push(e);
+                        }
+                    }
+                }
+                Err(e) => {
+                    println!("  error:   {e}");
+                    errors.push(e);
+                }
+            }
+        }
+        println!();
+    }
+    errors
+}
+
+pub fn ip(ipv4: bool, ipv6: bool) -> Result<(), Error> {
+    // 两个都没有指定时检测两种地址
+    let ip_versions = match (ipv4, ipv6) {
+        (true, false) => vec![RecordType::A],
+        (false, true) => vec![RecordType::AAAA],
+        _ => vec![RecordType::A, RecordType::AAAA],
+    };
+
+    let errors = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Runtime(i18n::runtime_create_failed(e)))?
+        .block_on(detect_all(&ip_versions));
+
+    match errors.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,129 @@
+use crate::cloudflare::{self, RecordInfo, Zone};
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::{DnsRecord, RecordType};
+use crate::initialize::parse_args::EmitFormat;
+use crate::secret::Secret;
+use crate::tools::print_table;
+
+struct ZoneRecords {
+    api_token: Secret,
+    zone: Zone,
+    records: Vec<RecordInfo>,
+}
+
+async fn discover(tokens: Vec<Secret>) -> Result<Vec<ZoneRecords>, Error> {
+    let mut tokens = tokens;
+    tokens.sort();
+    tokens.dedup();
+
+    let mut found: Vec<ZoneRecords> = Vec::new();
+    for api_token in tokens {
+        for zone in cloudflare::list_zones(api_token.expose()).await? {
+            // 多个 token 可能有同一个区域的权限
+            if found.iter().any(|known| known.zone.id == zone.id) {
+                continue;
+            }
+            let records = cloudflare::list_records(api_token.expose(), &zone.id)
+                .await?
+                .into_iter()
+                .filter(|record| matches!(record.record_type.as_str(), "A" | "AAAA"))
+                .collect();
+            found.push(ZoneRecords {
+                api_token: api_token.clone(),
+                zone,
+                records,
+            });
+        }
+    }
+    Ok(found)
+}
+
+pub fn to_dns_record(api_token: &Secret, zone_id: &str, record: &RecordInfo) -> DnsRecord {
+    DnsRecord {
+        api_token: api_token.clone(),
+        zone_id: zone_id.to_string(),
+        dns_id: record.id.clone(),
+        record_type: if record.record_type == "A" {
+            RecordType::A
+        } else {
+            RecordType::AAAA
+        },
+        name: record.name.clone(),
+        ttl: record.ttl,
+        proxied: record.proxied,
+    }
+}
+
+pub fn list(tokens: Vec<Secret>, emit: Option<EmitFormat>) -> Result<(), Error> {
+    if tokens.is_empty() {
+        return Err(Error::Config(i18n::records_no_token()));
+    }
+    let found = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Runtime(i18n::runtime_create_failed(e)))?
+        .block_on(discover(tokens))?;
+
+    match emit {
+        None => print_zones(&found),
+        Some(format) => print_config(&found, format)?,
+    }
+    Ok(())
+}
+
+fn print_zones(found: &[ZoneRecords]) {
+    if found.is_empty() {
+        println!("{}", i18n::records_no_zone());
+        return;
+    }
+    let headers = ["TYPE", "NAME", "CONTENT", "TTL", "PROXIED", "DNS_ID"];
+    for zone in found {
+        println!("{} (zone_id: {})", zone.zone.name, zone.zone.id);
+        if zone.records.is_empty() {
+            println!("  {}", i18n::records_zone_empty());
+            println!();
+            continue;
+        }
+        let rows: Vec<Vec<String>> = zone
+            .records
+            .iter()
+            .map(|record| {
+                vec![
+                    record.record_type.clone(),
+                    record.name.clone(),
+                    record.content.clone(),
+                    record.ttl.to_string(),
+                    record.proxied.to_string(),
+                    record.id.clone(),
+                ]
+            })
+            .collect();
+        print_table("  ", &headers, &rows);
+        println!();
+    }
+}
+
+fn print_config(found: &[ZoneRecords], format: EmitFormat) -> Result<(), Error> {
+    #[derive(serde::Serialize)]
+    struct Records {
+        dns_records: Vec<DnsRecord>,
+    }
+    let records = Records {
+        dns_records: found
+            .iter()
+            .flat_map(|zone| {
+                zone.records
+                    .iter()
+                    .map(|record| to_dns_record(&zone.api_token, &zone.zone.id, record))
+            })
+            .collect(),
+    };
+    let output = match format {
+        EmitFormat::Toml => toml::to_string(&records).map_err(|e| e.to_string()),
+        EmitFormat::Json => serde_json::to_string_pretty(&records).map_err(|e| e.to_string()),
+    }
+    .map_err(|e| Error::Runtime(i18n::records_serialize_failed(e)))?;
+    println!("{output}");
+    Ok(())
+}
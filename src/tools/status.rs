@@ -0,0 +1,88 @@
+use chrono::{DateTime, Local, Utc};
+
+use crate::error::Error;
+use crate::i18n;
+use crate::state::State;
+use crate::tools::print_table;
+
+fn format_time(time: Option<DateTime<Utc>>) -> String {
+    time.map_or_else(
+        || "-".to_string(),
+        |time| {
+            time.with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        },
+    )
+}
+
+pub fn status(json: bool) -> Result<(), Error> {
+    let state = State::read()?;
+    if json {
+        let output = serde_json::to_string_pretty(&state)
+            .map_err(|e| Error::Runtime(i18n::records_serialize_failed(e)))?;
+        println!("{output}");
+        return Ok(());
+    }
+
+    for (label, detection, pending) in [
+        ("IPv4", &state.ipv4, &state.ipv4_pending),
+        ("IPv6", &state.ipv6, &state.ipv6_pending),
+    ] {
+        match detection {
+            Some(detection) => println!(
+                "{label}: {} ({}, {})",
+                detection.address,
+                format_time(Some(detection.time)),
+                detection.source
+            ),
+            None => println!("{label}: -"),
+        }
+        if let Some(pending) = pending {
+            println!(
+                "  {}",
+                i18n::status_pending(
+                    pending.address,
+                    format_time(Some(pending.since)),
+                    pending.checks,
+                    (Utc::now() - pending.since).num_seconds()
+                )
+            );
+        }
+    }
+    println!();
+
+    if state.records.is_empty() {
+        println!("{}", i18n::status_no_records(State::path().display()));
+        return Ok(());
+    }
+    let headers = [
+        "TYPE",
+        "NAME",
+        "CONTENT",
+        "LAST_SUCCESS",
+        "FAILURES",
+        "NEXT_RETRY",
+        "LAST_ERROR",
+    ];
+    let rows: Vec<Vec<String>> = state
+        .records
+        .iter()
+        .map(|record| {
+            vec![
+                record.record_type.as_str().to_string(),
+                record.name.clone(),
+                record.content.clone().unwrap_or_else(|| "-".to_string()),
+                format_time(record.last_success),
+                record.consecutive_failures.to_string(),
+                format_time(record.next_retry),
+                match &record.last_error {
+                    Some(error) => format!("{} {error}", format_time(record.last_error_time)),
+                    None => "-".to_string(),
+                },
+            ]
+        })
+        .collect();
+    print_table("", &headers, &rows);
+    Ok(())
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use zeroize::Zeroize;
+
+use crate::cloudflare::{self, RecordBody};
+use crate::error::Error;
+use crate::i18n;
+use crate::initialize::load_conf::{self, Config, DnsRecord, RecordType};
+use crate::obj::{ARGS, DATA_DIR};
+use crate::run::detect::get_ip;
+use crate::secret::Secret;
+use crate::tools::records::to_dns_record;
+
+fn io_error(e: io::Error) -> Error {
+    Error::Runtime(i18n::wizard_io_failed(e))
+}
+
+fn prompt(question: &str, default: &str) -> Result<String, Error> {
+    if default.is_empty() {
+        print!("{question}: ");
+    } else {
+        print!("{question} [{default}]: ");
+    }
+    io::stdout().flush().map_err(io_error)?;
+
+    let mut line = String::new();
+    if io::stdin().lock().read_line(&mut line).map_err(io_error)? == 0 {
+        return Err(Error::Runtime(i18n::wizard_input_closed()));
+    }
+    let line = line.trim();
+    Ok(if line.is_empty() { default } else { line }.to_string())
+}
+
+fn prompt_parse<T: FromStr>(
+    question: &str,
+    default: &str,
+    valid: impl Fn(&T) -> bool,
+) -> Result<T, Error> {
+    loop {
+        match prompt(question, default)?.parse() {
+            Ok(value) if valid(&value) => return Ok(value),
+            _ => println!("{}", i18n::wizard_invalid_input()),
+        }
+    }
+}
+
+fn confirm(question: &str, default: bool) -> Result<bool, Error> {
+    let hint = if default { "Y/n" } else { "y/N" };
+    loop {
+        match prompt(&format!("{question} ({hint})"), "")?
+            .to_lowercase()
+            .as_str()
+        {
+            "" => return Ok(default),
+            "y" | "yes" => return Ok(true),
+            "n" | "no" => return Ok(false),
+            _ => println!("{}", i18n::wizard_invalid_input()),
+        }
+    }
+}
+
+/// 读取 "1,3"、"1 3" 或 "all"，返回从 0 开始的下标
+fn select(question: &str, count: usize) -> Result<Vec<usize>, Error> {
+    loop {
+        let answer = prompt(question, "")?;
+        if answer.eq_ignore_ascii_case("all") {
+            return Ok((0..count).collect());
+        }
+        let picked: Result<Vec<usize>, _> = answer
+            .split([',', ' '])
+            .filter(|item| !item.is_empty())
+            .map(|item| match item.parse::<usize>() {
+                Ok(i) if (1..=count).contains(&i) => Ok(i - 1),
+                _ => Err(()),
+            })
+            .collect();
+        match picked {
+            Ok(mut picked) => {
+                picked.sort();
+                picked.dedup();
+                return Ok(picked);
+            }
+            Err(()) => println!("{}", i18n::wizard_invalid_input()),
+        }
+    }
+}
+
+async fn wizard() -> Result<Config, Error> {
+    let mut input =
+        rpassword::prompt_password(format!("{}: ", i18n::wizard_token_prompt())).map_err(io_error)?;
+    let api_token = Secret::new(input.trim());
+    input.zeroize();
+    match cloudflare::verify_token(api_token.expose()).await?.status.as_str() {
+        "active" => println!("{}", i18n::wizard_token_ok()),
+        status => return Err(Error::Config(i18n::token_inactive(status))),
+    }
+
+    let zones = cloudflare::list_zones(api_token.expose()).await?;
+    if zones.is_empty() {
+        return Err(Error::Config(i18n::records_no_zone()));
+    }
+    zones
+        .iter()
+        .enumerate()
+        .for_each(|(i, zone)| println!("  {}. {}", i + 1, zone.name));
+    let picked_zones = select(&i18n::wizard_select_zones(), zones.len())?;
+
+    let ttl = prompt_parse(&i18n::wizard_ttl(), "1", |ttl: &u32| {
+        *ttl == 1 || (60..=86400).contains(ttl)
+    })?;
+    let proxied = confirm(&i18n::wizard_proxied(), false)?;
+
+    let mut dns_records: Vec<DnsRecord> = Vec::new();
+    for zone in picked_zones.into_iter().map(|i| &zones[i]) {
+        println!("\n{} (zone_id: {})", zone.name, zone.id);
+        let records: Vec<_> = cloudflare::list_records(api_token.expose(), &zone.id)
+            .await?
+            .into_iter()
+            .filter(|record| matches!(record.record_type.as_str(), "A" | "AAAA"))
+            .collect();
+        records.iter().enumerate().for_each(|(i, record)| {
+            println!(
+                "  {}. {} {} {}",
+                i + 1,
+                record.record_type,
+                record.name,
+                record.content
+            )
+        });
+        if !records.is_empty() {
+            for i in select(&i18n::wizard_select_records(), records.len())? {
+                dns_records.push(DnsRecord {
+                    ttl,
+                    proxied,
+                    ..to_dns_record(&api_token, &zone.id, &records[i])
+                });
+            }
+        }
+
+        loop {
+            let name = prompt(&i18n::wizard_new_record(&zone.name), "")?;
+            if name.is_empty() {
+                break;
+            }
+            // 允许只输入子域名部分
+            let name = if name == zone.name || name.ends_with(&format!(".{}", zone.name)) {
+                name
+            } else {
+                format!("{name}.{}", zone.name)
+            };
+            let record_type = match prompt_parse(&i18n::wizard_record_type(), "A", |t: &String| {
+                matches!(t.to_uppercase().as_str(), "A" | "AAAA")
+            })?
+            .to_uppercase()
+            .as_str()
+            {
+                "A" => RecordType::A,
+                _ => RecordType::AAAA,
+            };
+            // 记录在确认并写入配置后才创建，dns_id 留空，运行时按名称查找
+            dns_records.push(DnsRecord {
+                api_token: api_token.clone(),
+                zone_id: zone.id.clone(),
+                dns_id: String::new(),
+                record_type,
+                name,
+                ttl,
+                proxied,
+            });
+        }
+    }
+    if dns_records.is_empty() {
+        return Err(Error::Config(i18n::no_records_configured()));
+    }
+
+    let delay = prompt_parse(
+        &i18n::wizard_delay(),
+        &load_conf::get_default_delay().to_string(),
+        |delay: &u64| *delay > 0,
+    )?;
+    let ipv4_url = prompt_parse(
+        &i18n::wizard_ip_url(4),
+        load_conf::get_default_ipv4_url().as_str(),
+        |_: &url::Url| true,
+    )?;
+    let ipv6_url = prompt_parse(
+        &i18n::wizard_ip_url(6),
+        load_conf::get_default_ipv6_url().as_str(),
+        |_: &url::Url| true,
+    )?;
+
+    Ok(Config {
+        delay,
+        retry_delay: load_conf::get_default_retry_delay(),
+        max_retry_delay: load_conf::get_default_max_retry_delay(),
+        jitter: load_conf::get_default_jitter(),
+        schedule: None,
+        quiet_hours: Vec::new(),
+        force_refresh: 0,
+        stable_for: load_conf::StableFor::default(),
+        ipv4_allow: Vec::new(),
+        ipv4_deny: Vec::new(),
+        ipv6_allow: Vec::new(),
+        ipv6_deny: Vec::new(),
+        mutli_thread: load_conf::get_default_mutli_thread(),
+        watch_network: load_conf::get_default_watch_network(),
+        network_debounce: load_conf::get_default_network_debounce(),
+        log_level: load_conf::get_default_log_level(),
+        ipv4_url,
+        ipv6_url,
+        ipv4_sources: Vec::new(),
+        ipv6_sources: Vec::new(),
+        lang: None,
+        dns_records,
+    })
+}
+
+fn write_config(path: &Path, config: &Config) -> Result<(), Error> {
+    let content = if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+        serde_json::to_string_pretty(config).map_err(|e| e.to_string())
+    } else {
+        toml::to_string(config).map_err(|e| e.to_string())
+    }
+    .map_err(|e| Error::Runtime(i18n::records_serialize_failed(e)))?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(io_error)?;
+    }
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    // 配置中包含 API Token，只允许所有者读写
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = options.open(path).map_err(io_error)?;
+    #[cfg(unix)]
+    file.set_permissions(fs::Permissions::from_mode(0o600))
+        .map_err(io_error)?;
+    file.write_all(content.as_bytes()).map_err(io_error)
+}
+
+/// 用当前检测到的地址创建向导中新建的记录
+async fn create_records(config: Config, path: PathBuf) -> Result<(), Error> {
+    let new_records: Vec<DnsRecord> = config
+        .dns_records
+        .iter()
+        .filter(|record| record.dns_id.is_empty())
+        .cloned()
+        .collect();
+    // 检测 IP 需要使用刚写入的配置中的来源
+    Config::swap(config, Some(path));
+
+    let mut detected = HashMap::new();
+    for record in &new_records {
+        let ip = match detected.get(&record.record_type) {
+            Some(ip) => *ip,
+            None => {
+                let ip = get_ip(record.record_type).await?.ip;
+                detected.insert(record.record_type, ip);
+                ip
+            }
+        };
+        let created = cloudflare::create_record(
+            record.api_token.expose(),
+            &record.zone_id,
+            &RecordBody {
+                record_type: record.record_type.as_str(),
+                name: &record.name,
+                content: ip.to_string(),
+                ttl: record.ttl,
+                proxied: record.proxied,
+            },
+        )
+        .await?;
+        println!(
+            "{}",
+            i18n::wizard_record_created(&created.name, &created.id)
+        );
+    }
+    Ok(())
+}
+
+pub fn init() -> Result<(), Error> {
+    // 优先覆盖 --config 指定或已经存在的配置文件
+    let path = ARGS
+        .config
+        .clone()
+        .or_else(|| load_conf::find_config().ok())
+        .unwrap_or_else(|| DATA_DIR.join("config.toml"));
+    if path.exists() && !confirm(&i18n::wizard_overwrite(path.display()), false)? {
+        return Ok(());
+    }
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(|e| Error::Runtime(i18n::runtime_create_failed(e)))?;
+    let config = runtime.block_on(wizard())?;
+
+    // 确认之前不写入文件，也不在 Cloudflare 上创建任何记录
+    let new_records = config
+        .dns_records
+        .iter()
+        .filter(|record| record.dns_id.is_empty())
+        .count();
+    if !confirm(
+        &i18n::wizard_confirm(path.display(), config.dns_records.len(), new_records),
+        true,
+    )? {
+        return Ok(());
+    }
+    write_config(&path, &config)?;
+    println!("{}", i18n::wizard_written(path.display()));
+
+    if new_records > 0 {
+        runtime.block_on(create_records(config, path)).inspect_err(|_| {
+            println!("{}", i18n::wizard_create_failed());
+        })?;
+    }
+    Ok(())
+}